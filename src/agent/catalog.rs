@@ -0,0 +1,222 @@
+use {
+    crate::config::did_you_mean,
+    serde::Serialize,
+    std::collections::{BTreeSet, HashSet},
+};
+
+/// Version of the built-in catalog below, bumped whenever a Kiro release adds,
+/// renames or removes a tool
+pub const CATALOG_VERSION: u32 = 1;
+
+/// `@builtin` in `tools` selects every built-in tool
+pub const BUILTIN: &str = "@builtin";
+
+/// Built-in tools of kiro-cli, canonical name first, then aliases
+const BUILT_IN_TOOLS: &[(&str, &[&str])] = &[
+    ("fs_read", &["read"]),
+    ("fs_write", &["write"]),
+    ("execute_bash", &["shell"]),
+    ("use_aws", &["aws"]),
+    ("knowledge", &[]),
+    ("web_search", &[]),
+    ("web_fetch", &["fetch"]),
+    ("introspect", &[]),
+    ("report_issue", &[]),
+    ("thinking", &[]),
+    ("todo_list", &[]),
+    ("delegate", &[]),
+    ("use_subagent", &[]),
+];
+
+/// A built-in tool and the names that refer to it
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct BuiltInTool {
+    pub name: String,
+    pub aliases: Vec<String>,
+}
+
+/// Known built-in tool names, the shipped catalog plus `builtin-tool`
+/// extensions from kg.kdl
+#[derive(Clone, Debug, Serialize)]
+pub struct ToolCatalog {
+    pub version: u32,
+    tools: Vec<BuiltInTool>,
+}
+
+impl Default for ToolCatalog {
+    fn default() -> Self {
+        Self {
+            version: CATALOG_VERSION,
+            tools: BUILT_IN_TOOLS
+                .iter()
+                .map(|(name, aliases)| BuiltInTool {
+                    name: name.to_string(),
+                    aliases: aliases.iter().map(|a| a.to_string()).collect(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl ToolCatalog {
+    /// Add tools of a newer Kiro release, aliases of a known tool are added
+    /// to it
+    pub fn extend(&mut self, tools: impl IntoIterator<Item = BuiltInTool>) {
+        for tool in tools {
+            match self.tools.iter_mut().find(|t| t.name == tool.name) {
+                Some(known) => {
+                    for alias in tool.aliases {
+                        if !known.aliases.contains(&alias) {
+                            known.aliases.push(alias);
+                        }
+                    }
+                }
+                None => self.tools.push(tool),
+            }
+        }
+    }
+
+    /// Every name and alias
+    fn names(&self) -> impl Iterator<Item = &str> {
+        self.tools
+            .iter()
+            .flat_map(|t| std::iter::once(&t.name).chain(t.aliases.iter()))
+            .map(String::as_str)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.names().any(|n| n == name)
+    }
+
+    /// Closest known name for a misspelled tool
+    pub fn suggest(&self, name: &str) -> Option<&str> {
+        did_you_mean(name, self.names())
+    }
+
+    /// Canonical name of a built-in tool or one of its aliases
+    fn canonical(&self, name: &str) -> Option<&str> {
+        self.tools
+            .iter()
+            .find(|t| t.name == name || t.aliases.iter().any(|a| a == name))
+            .map(|t| t.name.as_str())
+    }
+
+    /// Whether a hook `matcher` selects `tool`. `*` selects every tool,
+    /// `@builtin` every built-in one, `@server` every tool of that server,
+    /// anything else is a glob on the tool name or names the same built-in
+    /// tool by an alias
+    pub fn matches(&self, matcher: &str, tool: &str) -> bool {
+        if matcher == "*" {
+            return true;
+        }
+        if matcher == BUILTIN {
+            return !tool.starts_with('@');
+        }
+        if let Some(server) = matcher.strip_prefix('@')
+            && !server.contains('/')
+        {
+            return tool
+                .strip_prefix('@')
+                .is_some_and(|t| t == server || t.starts_with(&format!("{server}/")));
+        }
+        if glob::Pattern::new(matcher).is_ok_and(|p| p.matches(tool)) {
+            return true;
+        }
+        self.canonical(matcher)
+            .is_some_and(|m| self.canonical(tool) == Some(m))
+    }
+
+    /// Whether a matcher selects at least one built-in tool
+    pub fn matches_any(&self, matcher: &str) -> bool {
+        self.names().any(|n| self.matches(matcher, n))
+    }
+
+    /// Tool names the catalog cannot check: wildcards, `@builtin` and MCP
+    /// server references
+    fn is_checkable(name: &str) -> bool {
+        !name.starts_with('@') && !name.contains(['*', '?'])
+    }
+
+    /// Names in `tools` that are neither built-in tools nor references the
+    /// catalog cannot check, with the closest known name
+    pub fn unknown<'a>(
+        &self,
+        tools: impl IntoIterator<Item = &'a String>,
+    ) -> Vec<(String, Option<String>)> {
+        let unknown: BTreeSet<&String> = tools
+            .into_iter()
+            .filter(|t| Self::is_checkable(t) && !self.contains(t))
+            .collect();
+        unknown
+            .into_iter()
+            .map(|t| (t.clone(), self.suggest(t).map(String::from)))
+            .collect()
+    }
+
+    /// `tools` with `*` replaced by `@builtin` and every enabled server as
+    /// `@server`. Built-in tools `@builtin` covers are left out, so the list
+    /// stays short enough for the table
+    pub fn expand(&self, tools: &HashSet<String>, servers: &[String]) -> Vec<String> {
+        let all = tools.contains("*");
+        let builtin = all || tools.contains(BUILTIN);
+        let mut expanded: BTreeSet<String> = tools
+            .iter()
+            .filter(|t| *t != "*" && !(builtin && self.contains(t)))
+            .cloned()
+            .collect();
+        if builtin {
+            expanded.insert(BUILTIN.to_string());
+        }
+        if all {
+            expanded.extend(servers.iter().map(|s| format!("@{s}")));
+        }
+        expanded.into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_catalog() {
+        let mut catalog = ToolCatalog::default();
+        assert!(catalog.contains("read"));
+        assert!(catalog.contains("fs_read"));
+        let tools: Vec<String> = ["web_serch", "fs_raed", "@github", "web_search", "@git/*"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        assert_eq!(catalog.unknown(&tools), vec![
+            ("fs_raed".to_string(), Some("fs_read".to_string())),
+            ("web_serch".to_string(), Some("web_search".to_string())),
+        ]);
+
+        catalog.extend([BuiltInTool {
+            name: "code".into(),
+            aliases: vec!["code_search".into()],
+        }]);
+        assert!(catalog.contains("code_search"));
+
+        let tools = |names: &[&str]| names.iter().map(|n| n.to_string()).collect();
+        let expanded = catalog.expand(&tools(&["*", "@github/get_issue", "code"]), &[
+            "github".to_string()
+        ]);
+        assert_eq!(expanded, vec!["@builtin", "@github", "@github/get_issue"]);
+        let expanded = catalog.expand(&tools(&["@builtin", "read"]), &["github".to_string()]);
+        assert_eq!(expanded, vec!["@builtin"]);
+        let expanded = catalog.expand(&tools(&["read", "fs_write"]), &[]);
+        assert_eq!(expanded, vec!["fs_write", "read"]);
+
+        assert!(catalog.matches("*", "@github/get_issue"));
+        assert!(catalog.matches("@builtin", "fs_write"));
+        assert!(!catalog.matches("@builtin", "@github"));
+        assert!(catalog.matches("@github", "@github/get_issue"));
+        assert!(!catalog.matches("@git", "@github/get_issue"));
+        assert!(catalog.matches("fs_*", "fs_read"));
+        assert!(catalog.matches("shell", "execute_bash"));
+        assert!(!catalog.matches("shell", "fs_read"));
+        assert!(catalog.matches_any("web_*"));
+        assert!(!catalog.matches_any("git_*"));
+    }
+}
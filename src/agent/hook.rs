@@ -1,29 +1,55 @@
 use {
     serde::{Deserialize, Serialize},
-    std::fmt::Display,
+    std::{fmt::Display, path::PathBuf},
 };
 
 const DEFAULT_TIMEOUT_MS: u64 = 30_000;
 const DEFAULT_MAX_OUTPUT_SIZE: u64 = 1024 * 10;
 const DEFAULT_CACHE_TTL_SECONDS: u64 = 0;
+/// Longer than this is more likely a typo than a hook
+const MAX_TIMEOUT_MS: u64 = 10 * 60 * 1000;
+/// More than this floods the context
+const MAX_OUTPUT_SIZE: u64 = 1024 * 1024;
 
 #[derive(
-    Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq, Hash, enum_iterator::Sequence,
+    Debug,
+    Clone,
+    Copy,
+    Serialize,
+    Deserialize,
+    Eq,
+    PartialEq,
+    Hash,
+    PartialOrd,
+    Ord,
+    enum_iterator::Sequence,
+    clap::ValueEnum,
 )]
 #[serde(rename_all = "camelCase")]
 pub enum HookTrigger {
     /// Triggered during agent spawn
+    #[value(alias = "agentSpawn")]
     AgentSpawn,
     /// Triggered per user message submission
+    #[value(alias = "userPromptSubmit")]
     UserPromptSubmit,
     /// Triggered before tool execution
+    #[value(alias = "preToolUse")]
     PreToolUse,
     /// Triggered after tool execution
+    #[value(alias = "postToolUse")]
     PostToolUse,
     /// Triggered when the assistant finishes responding
     Stop,
 }
 
+impl HookTrigger {
+    /// `preToolUse` and `postToolUse`, the triggers `matcher` applies to
+    pub fn is_tool_use(&self) -> bool {
+        matches!(self, Self::PreToolUse | Self::PostToolUse)
+    }
+}
+
 impl Display for HookTrigger {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -57,10 +83,45 @@ pub struct Hook {
     /// Currently used for matching tool name of PreToolUse and PostToolUse hook
     #[serde(skip_serializing_if = "Option::is_none")]
     pub matcher: Option<String>,
+
+    /// Script kg writes to `.kiro/hooks` and sets `command` to, never part
+    /// of the Kiro JSON
+    #[serde(skip)]
+    pub script: Option<HookScript>,
+
+    /// `disabled=#true`, the hook is left out of the Kiro JSON
+    #[serde(skip)]
+    pub disabled: bool,
+
+    /// `replace=#true`, the hook does not take settings from the parent's
+    /// hook of the same name
+    #[serde(skip)]
+    pub replace: bool,
+
+    /// `before="name"`, run right before that hook of the same trigger
+    #[serde(skip)]
+    pub before: Option<String>,
+
+    /// `after="name"`, run right after that hook of the same trigger
+    #[serde(skip)]
+    pub after: Option<String>,
+}
+
+/// Script of a hook, a file or the script itself
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum HookScript {
+    /// `script-file "hooks/whoami.sh"` or `script "hooks/whoami.sh"` naming an
+    /// existing file, relative to the file declaring it
+    File(PathBuf),
+    /// `script "..."`, the script itself
+    Inline(String),
 }
 
 impl Hook {
     pub fn merge(mut self, o: Self) -> Self {
+        if self.replace {
+            return self;
+        }
         if self.cache_ttl_seconds == 0 {
             self.cache_ttl_seconds = if o.cache_ttl_seconds == 0 {
                 DEFAULT_CACHE_TTL_SECONDS
@@ -68,8 +129,11 @@ impl Hook {
                 o.cache_ttl_seconds
             };
         }
-        if self.command.is_empty() {
+        // command and script are one setting, a child's script replaces a
+        // parent's command
+        if self.command.is_empty() && self.script.is_none() {
             self.command = o.command;
+            self.script = o.script;
         }
         if self.max_output_size == 0 {
             self.max_output_size = if o.max_output_size == 0 {
@@ -88,9 +152,48 @@ impl Hook {
         if self.matcher.is_none() && o.matcher.is_some() {
             self.matcher = o.matcher;
         }
+        if self.before.is_none() && self.after.is_none() {
+            self.before = o.before;
+            self.after = o.after;
+        }
         self
     }
 
+    /// `timeout_ms`, Kiro's default when unset
+    pub fn effective_timeout_ms(&self) -> u64 {
+        match self.timeout_ms {
+            0 => DEFAULT_TIMEOUT_MS,
+            ms => ms,
+        }
+    }
+
+    /// `max_output_size`, Kiro's default when unset
+    pub fn effective_max_output_size(&self) -> u64 {
+        match self.max_output_size {
+            0 => DEFAULT_MAX_OUTPUT_SIZE,
+            size => size,
+        }
+    }
+
+    /// Absurd `timeout_ms` and `max_output_size`, Kiro takes them as they
+    /// are. 0 is unset, see [`Self::effective_timeout_ms`]
+    pub fn limit_warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        if self.timeout_ms > MAX_TIMEOUT_MS {
+            warnings.push(format!(
+                "has timeout-ms {}, more than {MAX_TIMEOUT_MS}",
+                self.timeout_ms
+            ));
+        }
+        if self.max_output_size > MAX_OUTPUT_SIZE {
+            warnings.push(format!(
+                "has max-output-size {}, more than {MAX_OUTPUT_SIZE}",
+                self.max_output_size
+            ));
+        }
+        warnings
+    }
+
     fn default_timeout_ms() -> u64 {
         DEFAULT_TIMEOUT_MS
     }
@@ -121,6 +224,33 @@ mod tests {
         assert_eq!(Hook::default_cache_ttl_seconds(), 0);
     }
 
+    #[test]
+    fn hook_limit_warnings() {
+        let hook = Hook {
+            command: "test".into(),
+            timeout_ms: 2 * MAX_TIMEOUT_MS,
+            max_output_size: 10 * MAX_OUTPUT_SIZE,
+            cache_ttl_seconds: 0,
+            matcher: None,
+            script: None,
+            disabled: false,
+            replace: false,
+            before: None,
+            after: None,
+        };
+        let warnings = hook.limit_warnings();
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].contains("timeout-ms 1200000"));
+        assert!(warnings[1].contains("max-output-size 10485760"));
+        // unset limits take Kiro's defaults
+        let hook = Hook {
+            timeout_ms: 0,
+            max_output_size: 0,
+            ..hook
+        };
+        assert!(hook.limit_warnings().is_empty());
+    }
+
     #[test]
     fn hook_serde() {
         let hook = Hook {
@@ -129,6 +259,11 @@ mod tests {
             max_output_size: 500,
             cache_ttl_seconds: 10,
             matcher: Some("*.rs".into()),
+            script: None,
+            disabled: false,
+            replace: false,
+            before: None,
+            after: None,
         };
         let json = serde_json::to_string(&hook).unwrap();
         let deserialized: Hook = serde_json::from_str(&json).unwrap();
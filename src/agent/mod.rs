@@ -1,3 +1,4 @@
+mod catalog;
 mod custom_tool;
 pub mod hook;
 pub mod tools;
@@ -9,11 +10,12 @@ use {
     miette::IntoDiagnostic,
     serde::{Deserialize, Serialize},
     std::{
-        collections::{HashMap, HashSet},
+        collections::{BTreeMap, HashMap, HashSet},
         fmt::Display,
     },
 };
 pub use {
+    catalog::{BUILTIN, BuiltInTool, ToolCatalog},
     custom_tool::{CustomToolConfig, tool_default_timeout},
     tools::*,
 };
@@ -52,7 +54,7 @@ pub struct Agent {
     pub resources: HashSet<String>,
     /// Commands to run when a chat session is created
     #[serde(default)]
-    pub hooks: HashMap<HookTrigger, Vec<Hook>>,
+    pub hooks: BTreeMap<HookTrigger, Vec<Hook>>,
     /// Settings for specific tools. These are mostly for native tools. The
     /// actual schema differs by tools and is documented in detail in our
     /// documentation
@@ -73,7 +75,82 @@ impl Display for Agent {
 }
 
 impl Agent {
-    pub fn validate(&self) -> Result<()> {
+    /// Check a hook `matcher`: only tool use hooks have one, it must be a
+    /// valid glob and should select a built-in tool or an MCP server of the
+    /// agent
+    fn validate_matcher(
+        &self,
+        hook_name: &str,
+        trigger: &HookTrigger,
+        hook: &Hook,
+        catalog: &ToolCatalog,
+    ) -> Result<()> {
+        let Some(matcher) = &hook.matcher else {
+            return Ok(());
+        };
+        if !trigger.is_tool_use() {
+            return Err(crate::Error::invalid_agent(
+                &self.name,
+                format!("{hook_name} has matcher '{matcher}'"),
+            )
+            .with_help("matcher only applies to pre-tool-use and post-tool-use hooks")
+            .into());
+        }
+        if let Err(e) = glob::Pattern::new(matcher) {
+            return Err(crate::Error::invalid_agent(
+                &self.name,
+                format!("{hook_name} has invalid matcher '{matcher}': {e}"),
+            )
+            .into());
+        }
+        if matcher == "*" || matcher == BUILTIN {
+            return Ok(());
+        }
+        if let Some(reference) = matcher.strip_prefix('@') {
+            let server = reference.split('/').next().unwrap_or_default();
+            if !self.include_mcp_json && !self.mcp_servers.contains_key(server) {
+                tracing::warn!(
+                    target: crate::check::POLICY,
+                    "agent '{}': {hook_name} matcher '{matcher}' refers to unknown mcp server \
+                     '{server}'",
+                    self.name
+                );
+            }
+        } else if !catalog.matches_any(matcher) {
+            let help = catalog
+                .suggest(matcher)
+                .map(|s| format!(", did you mean '{s}'?"))
+                .unwrap_or_default();
+            tracing::warn!(
+                target: crate::check::POLICY,
+                "agent '{}': {hook_name} matcher '{matcher}' matches no built-in tool of catalog \
+                 v{}{help}",
+                self.name,
+                catalog.version
+            );
+        }
+        Ok(())
+    }
+
+    /// Check the hooks of `source`, the agent this one was generated from,
+    /// against `catalog`, then the agent against the Kiro JSON schema
+    pub fn validate(&self, source: &KdlAgent, catalog: &ToolCatalog) -> Result<()> {
+        for trigger in enum_iterator::all::<HookTrigger>() {
+            for (name, hook) in source.hook.named(&trigger) {
+                if hook.disabled {
+                    continue;
+                }
+                let hook_name = format!("{trigger} hook '{name}'");
+                self.validate_matcher(&hook_name, &trigger, hook, catalog)?;
+                for warning in hook.limit_warnings() {
+                    tracing::warn!(
+                        target: crate::check::POLICY,
+                        "agent '{}': {hook_name} {warning}",
+                        self.name
+                    );
+                }
+            }
+        }
         // TODO cache this
         let schema: serde_json::Value =
             serde_json::from_str(crate::schema::SCHEMA).into_diagnostic()?;
@@ -81,11 +158,15 @@ impl Agent {
         let instance = serde_json::to_value(self).into_diagnostic()?;
 
         if let Err(e) = validator.validate(&instance) {
-            return Err(crate::format_err!(
-                "Validation error: {}\n{}",
-                e,
+            return Err(crate::Error::invalid_agent(
+                &self.name,
+                format!("does not match the Kiro agent schema: {e}"),
+            )
+            .with_help(format!(
+                "the generated agent is {}",
                 serde_json::to_string(&instance).unwrap_or_default()
-            ));
+            ))
+            .into());
         }
         Ok(())
     }
@@ -159,7 +240,7 @@ impl TryFrom<&KdlAgent> for Agent {
         // let extra_tool_settings = value.extra_tool_settings()?;
         // tools_settings.extend(extra_tool_settings);
 
-        let mut hooks: HashMap<HookTrigger, Vec<Hook>> = HashMap::new();
+        let mut hooks: BTreeMap<HookTrigger, Vec<Hook>> = BTreeMap::new();
         let triggers: Vec<HookTrigger> = enum_iterator::all::<HookTrigger>().collect();
         for t in triggers {
             hooks.insert(t, value.hook.hooks(&t));
@@ -228,7 +309,10 @@ impl Default for Agent {
 
 #[cfg(test)]
 mod tests {
-    use super::*;
+    use {
+        super::*,
+        crate::config::{GeneratorConfig, GeneratorConfigDoc, kdl_parse},
+    };
 
     #[test]
     fn test_default_agent() -> crate::Result<()> {
@@ -244,4 +328,40 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_validate_matcher() -> crate::Result<()> {
+        let catalog = ToolCatalog::default();
+        let validate = |trigger: &str, matcher: &str| -> crate::Result<()> {
+            let doc: GeneratorConfigDoc = kdl_parse(&format!(
+                r#"agent "test" {{
+                    hook {{
+                        {trigger} "audit" {{
+                            command "true"
+                            matcher "{matcher}"
+                        }}
+                    }}
+                }}"#
+            ))?;
+            let config = GeneratorConfig::from(doc);
+            let kg_agent = config.get("test").expect("test");
+            Agent::try_from(kg_agent)?.validate(kg_agent, &catalog)
+        };
+        for matcher in ["*", "@builtin", "shell", "fs_*", "@github/get_issue"] {
+            validate("pre-tool-use", matcher)?;
+        }
+        let Err(err) = validate("agent-spawn", "shell") else {
+            panic!("agentSpawn hooks have no tool to match");
+        };
+        assert!(
+            err.to_string()
+                .contains("agentSpawn hook 'audit' has matcher 'shell'"),
+            "{err}"
+        );
+        let Err(err) = validate("post-tool-use", "fs_[") else {
+            panic!("unclosed character class");
+        };
+        assert!(err.to_string().contains("invalid matcher"), "{err}");
+        Ok(())
+    }
 }
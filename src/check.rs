@@ -0,0 +1,422 @@
+use {
+    crate::generator::{AgentResult, Drift},
+    miette::{Diagnostic, SourceCode, SourceSpan},
+    serde::Serialize,
+    std::{
+        fmt,
+        path::PathBuf,
+        sync::{Arc, Mutex},
+    },
+    tracing::{
+        Event,
+        Level,
+        Metadata,
+        Subscriber,
+        field::{Field, Visit},
+        span::{Attributes, Id},
+    },
+    tracing_subscriber::{
+        layer::{Context, Layer},
+        registry::LookupSpan,
+    },
+};
+
+/// Target of warnings about the configuration, the ones `kg check` fails on.
+/// Other warnings, e.g. about a file kg could not clean up, are only logged
+pub const POLICY: &str = "kg::policy";
+
+/// Exit codes of `kg check`. Any error kg stops on exits with 1 like every
+/// other command, invalid arguments with 2
+#[derive(Clone, Copy, Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum CheckStatus {
+    /// Valid, no warnings and the generated files are up to date
+    Ok = 0,
+    /// The config could not be loaded or is invalid, only in a report
+    /// written after the error
+    ConfigError = 1,
+    /// kg warned about the configuration, e.g. a dangling server reference
+    /// or an unknown tool
+    PolicyViolation = 3,
+    /// Valid, but a generated file is missing or differs
+    OutputDrift = 4,
+}
+
+impl fmt::Display for CheckStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ok => write!(f, "ok"),
+            Self::ConfigError => write!(f, "config error"),
+            Self::PolicyViolation => write!(f, "policy violation"),
+            Self::OutputDrift => write!(f, "output drift"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Error => write!(f, "error"),
+            Self::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// A warning kg logged while loading, merging or checking agents, or the
+/// error it stopped on, with the KDL file and line it is about when known
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+pub struct Finding {
+    pub severity: Severity,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agent: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+}
+
+impl Finding {
+    pub fn warning(message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            message: message.into(),
+            agent: None,
+            file: None,
+            line: None,
+        }
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            ..Self::warning(message)
+        }
+    }
+
+    pub fn at(mut self, file: impl Into<PathBuf>, line: Option<usize>) -> Self {
+        self.file = Some(file.into());
+        self.line = line;
+        self
+    }
+
+    /// The error kg stopped on, one finding per unknown field of a KDL file
+    pub fn from_error(err: &miette::Report) -> Vec<Self> {
+        match err.downcast_ref::<crate::Error>() {
+            Some(crate::Error::UnknownFields(unknown)) => unknown
+                .fields
+                .iter()
+                .map(|field| {
+                    let message = match &field.suggestion {
+                        Some(s) => format!("{field}, did you mean `{s}`?"),
+                        None => field.to_string(),
+                    };
+                    let line = unknown
+                        .source_code()
+                        .and_then(|source| line_of(source, &field.span));
+                    Self::error(message).at(&unknown.file, line)
+                })
+                .collect(),
+            Some(crate::Error::DeserializeError(file, kdl_err)) => {
+                let label = kdl_err.labels().and_then(|mut labels| labels.next());
+                let line = label
+                    .as_ref()
+                    .zip(kdl_err.source_code())
+                    .and_then(|(label, source)| line_of(source, label.inner()));
+                let message = match label.as_ref().and_then(|l| l.label()) {
+                    Some(text) => format!("{kdl_err}: {text}"),
+                    None => kdl_err.to_string(),
+                };
+                vec![Self::error(message).at(file, line)]
+            }
+            Some(crate::Error::InvalidAgent {
+                agent, file, line, ..
+            }) => {
+                let chain: Vec<String> = err.chain().map(ToString::to_string).collect();
+                let mut finding = Self::error(chain.join(": "));
+                finding.agent = Some(agent.clone());
+                finding.file = file.clone();
+                finding.line = *line;
+                vec![finding]
+            }
+            None => {
+                let chain: Vec<String> = err.chain().map(ToString::to_string).collect();
+                vec![Self::error(chain.join(": "))]
+            }
+        }
+    }
+}
+
+/// 1-based line of `span` in `source`
+fn line_of(source: &dyn SourceCode, span: &SourceSpan) -> Option<usize> {
+    source.read_span(span, 0, 0).ok().map(|c| c.line() + 1)
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.file, self.line) {
+            (Some(file), Some(line)) => write!(f, "{}:{line}: ", file.display())?,
+            (Some(file), None) => write!(f, "{}: ", file.display())?,
+            _ => {}
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Tracing layer that keeps every [`POLICY`] warning, so `kg check` can fail
+/// on them. A warning is about the agent of the span it is logged in, and
+/// about a place in a KDL file when it has `file` and `line` fields. Give it
+/// [`Findings::interested`] as its own filter, so the log filter, e.g.
+/// `RUST_LOG=error`, cannot hide a finding
+#[derive(Clone, Default)]
+pub struct Findings(Arc<Mutex<Vec<Finding>>>);
+
+impl Findings {
+    /// Policy warnings, and the spans that name their agent
+    pub fn interested(meta: &Metadata<'_>) -> bool {
+        match meta.is_span() {
+            true => *meta.level() <= Level::INFO,
+            false => meta.target() == POLICY && *meta.level() == Level::WARN,
+        }
+    }
+
+    /// The warnings collected so far, the collector is left empty
+    pub fn take(&self) -> Vec<Finding> {
+        self.0
+            .lock()
+            .map(|mut f| std::mem::take(&mut *f))
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Default)]
+struct FindingVisitor {
+    message: String,
+    agent: Option<String>,
+    file: Option<PathBuf>,
+    line: Option<usize>,
+}
+
+impl Visit for FindingVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "file" => self.file = Some(value.into()),
+            _ => self.record_debug(field, &value),
+        }
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        match field.name() {
+            "line" => self.line = usize::try_from(value).ok(),
+            _ => self.record_debug(field, &value),
+        }
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        match field.name() {
+            "line" => self.line = usize::try_from(value).ok(),
+            _ => self.record_debug(field, &value),
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        let value = format!("{value:?}");
+        match field.name() {
+            "message" => self.message = value,
+            "agent" => self.agent = Some(value),
+            "file" => self.file = Some(value.into()),
+            _ => {}
+        }
+    }
+}
+
+/// `agent` field of a span, e.g. the one `Generator::write` runs in
+struct SpanAgent(String);
+
+impl<S> Layer<S> for Findings
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut visitor = FindingVisitor::default();
+        attrs.record(&mut visitor);
+        if let (Some(agent), Some(span)) = (visitor.agent, ctx.span(id)) {
+            span.extensions_mut().insert(SpanAgent(agent));
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        if !Self::interested(event.metadata()) {
+            return;
+        }
+        let mut visitor = FindingVisitor::default();
+        event.record(&mut visitor);
+        let agent = visitor.agent.or_else(|| {
+            ctx.event_scope(event)?
+                .find_map(|span| span.extensions().get::<SpanAgent>().map(|a| a.0.clone()))
+        });
+        if let Ok(mut findings) = self.0.lock() {
+            findings.push(Finding {
+                severity: Severity::Warning,
+                message: visitor.message,
+                agent,
+                file: visitor.file,
+                line: visitor.line,
+            });
+        }
+    }
+}
+
+/// Outcome of `kg check`
+#[derive(Debug, Serialize)]
+pub struct CheckReport {
+    pub status: CheckStatus,
+    /// Non-template agents checked
+    pub agents: usize,
+    pub findings: Vec<Finding>,
+    pub drift: Vec<Drift>,
+}
+
+impl CheckReport {
+    pub fn new(results: &[AgentResult], findings: Vec<Finding>, drift: Vec<Drift>) -> Self {
+        let status = if findings.iter().any(|f| f.severity == Severity::Error) {
+            CheckStatus::ConfigError
+        } else if !findings.is_empty() {
+            CheckStatus::PolicyViolation
+        } else if !drift.is_empty() {
+            CheckStatus::OutputDrift
+        } else {
+            CheckStatus::Ok
+        };
+        Self {
+            status,
+            agents: results.iter().filter(|r| r.writable).count(),
+            findings,
+            drift,
+        }
+    }
+
+    /// kg stopped on `err`, the warnings logged before it are kept
+    pub fn failed(err: &miette::Report, mut findings: Vec<Finding>) -> Self {
+        findings.extend(Finding::from_error(err));
+        Self::new(&[], findings, vec![])
+    }
+
+    pub fn exit_code(&self) -> u8 {
+        self.status as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{generator::ConfigLocation, os::Fs},
+        miette::IntoDiagnostic,
+        tracing_subscriber::{EnvFilter, filter::filter_fn, prelude::*},
+    };
+
+    #[test]
+    fn test_findings() {
+        let findings = Findings::default();
+        let subscriber = tracing_subscriber::registry()
+            .with(
+                findings
+                    .clone()
+                    .with_filter(filter_fn(Findings::interested)),
+            )
+            .with(
+                tracing_subscriber::fmt::layer()
+                    .with_test_writer()
+                    .with_filter(EnvFilter::new("error")),
+            );
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!(target: POLICY, "not a finding");
+            let span = tracing::info_span!("write", agent = %"rust");
+            let _enter = span.enter();
+            tracing::warn!("ignoring unreadable kg-scripts.json");
+            tracing::warn!(
+                target: POLICY,
+                "agent '{}': tools 'fs_raed' is not a built-in tool",
+                "rust"
+            );
+            tracing::warn!(
+                target: POLICY,
+                file = "kg.kdl",
+                line = 3,
+                "`allow` in `shell` is deprecated"
+            );
+        });
+        let found = findings.take();
+        assert_eq!(found, vec![
+            Finding {
+                agent: Some("rust".into()),
+                ..Finding::warning("agent 'rust': tools 'fs_raed' is not a built-in tool")
+            },
+            Finding {
+                agent: Some("rust".into()),
+                ..Finding::warning("`allow` in `shell` is deprecated").at("kg.kdl", Some(3))
+            },
+        ]);
+        assert_eq!(
+            found[1].to_string(),
+            "kg.kdl:3: `allow` in `shell` is deprecated"
+        );
+        assert!(findings.take().is_empty());
+
+        let report = CheckReport::new(&[], found, vec![]);
+        assert_eq!(report.status, CheckStatus::PolicyViolation);
+        assert_eq!(report.exit_code(), 3);
+        let report = CheckReport::new(&[], vec![], vec![Drift::missing("a.json".into())]);
+        assert_eq!(report.exit_code(), 4);
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn test_from_error() -> crate::Result<()> {
+        let fs = Fs::new();
+        let kg = PathBuf::from(".kiro").join("generators").join("kg.kdl");
+        let load = |content: &'static str| {
+            let fs = fs.clone();
+            let kg = kg.clone();
+            async move {
+                fs.write(&kg, content).await.into_diagnostic()?;
+                crate::generator::Generator::new(
+                    fs,
+                    ConfigLocation::Local,
+                    crate::output::OutputFormat::Table(false),
+                    &crate::config::Facts::host(),
+                )
+                .map(|_| ())
+            }
+        };
+
+        let Err(err) = load("agent \"dev\" {\n    tols \"x\"\n}").await else {
+            panic!("tols is unknown");
+        };
+        let report = CheckReport::failed(&err, vec![]);
+        assert_eq!(report.status, CheckStatus::ConfigError);
+        assert_eq!(report.findings, vec![
+            Finding::error("unknown node `tols` in `agent \"dev\"`, did you mean `tools`?")
+                .at(kg.display().to_string(), Some(2))
+        ]);
+
+        let Err(err) = load("agent \"dev\" {\n\n    tools \"x\n}").await else {
+            panic!("x is not closed");
+        };
+        let found = Finding::from_error(&err);
+        assert_eq!(found[0].severity, Severity::Error);
+        assert_eq!(found[0].line, Some(3));
+
+        let found = Finding::from_error(&crate::format_err!("profile 'x' not found"));
+        assert_eq!(found, vec![Finding::error("profile 'x' not found")]);
+        Ok(())
+    }
+}
@@ -1,11 +1,19 @@
 use {
-    crate::output::{ColorOverride, OutputFormat, OutputFormatArg},
+    crate::{
+        agent::hook::HookTrigger,
+        config::Facts,
+        generator::{AgentFilter, ConfigLayer},
+        output::{ColorOverride, OutputFormat, OutputFormatArg},
+    },
     clap::{
         Parser,
         Subcommand,
         builder::{Styles, styling::AnsiColor},
     },
-    std::{io::IsTerminal, path::PathBuf},
+    std::{
+        io::IsTerminal,
+        path::{Path, PathBuf},
+    },
 };
 
 /// Get the color styles for the CLI help menu.
@@ -32,10 +40,19 @@ pub struct Cli {
     /// Format of the console output
     #[arg(short = 'f', long,  global = true , default_value_t = OutputFormatArg::default())]
     pub format: OutputFormatArg,
+    /// Additional config directories merged below the global config, lowest
+    /// priority first. Defaults to $KG_CONFIG_PATH, then /etc/kiro/generators
+    #[arg(long, global = true, value_name = "DIR")]
+    pub config_path: Vec<PathBuf>,
     #[command(subcommand)]
     pub command: Command,
 }
 
+/// Environment variable with additional config directories, see
+/// [`Cli::base_layers`]
+pub const KG_CONFIG_PATH: &str = "KG_CONFIG_PATH";
+const SYSTEM_CONFIG_PATH: &str = "/etc/kiro/generators";
+
 #[derive(clap::Args, Clone, Default)]
 pub struct InitArgs {
     /// Directory where configuration will be created.
@@ -54,6 +71,89 @@ pub struct Args {
     /// Show template agents in output
     #[arg(long, default_value = "false")]
     pub show_templates: bool,
+    /// Write agent files into this directory instead of .kiro/agents,
+    /// overrides output-dir in kg.kdl
+    #[arg(long, value_name = "DIR")]
+    pub output_dir: Option<PathBuf>,
+    /// Only validate/generate these agents. Inheritance is still resolved
+    /// against all agents
+    #[arg(value_name = "AGENT_NAME")]
+    pub agents: Vec<String>,
+    /// Only validate/generate agents whose name matches this glob, can be
+    /// repeated
+    #[arg(long = "match", value_name = "GLOB")]
+    pub matches: Vec<String>,
+    /// Evaluate `when` blocks as if os, hostname or an environment variable
+    /// had this value, e.g. --assume os=linux --assume CI=true
+    #[arg(long, value_name = "KEY=VALUE")]
+    pub assume: Vec<String>,
+    /// Apply the patches of this `profile` block in kg.kdl after inheritance
+    #[arg(long, value_name = "NAME")]
+    pub profile: Option<String>,
+    /// Remove `@server` and `@server/tool` references to disabled MCP
+    /// servers from tools, allowed-tools and alias of the generated agents
+    #[arg(long, default_value = "false")]
+    pub drop_disabled_refs: bool,
+    /// Resolve `file://` resources, warn when one matches nothing and show
+    /// the files and bytes each pulls into context
+    #[arg(long, default_value = "false")]
+    pub check_resources: bool,
+    /// Look up every MCP server command and the first word of every hook
+    /// command on PATH and report the result per agent. Nothing is executed
+    #[arg(long, default_value = "false")]
+    pub check_commands: bool,
+}
+
+#[derive(clap::Args, Clone, Default)]
+pub struct GenerateArgs {
+    #[command(flatten)]
+    pub args: Args,
+    /// Print the Kiro JSON of a single agent to stdout, no files are written
+    #[arg(long, value_name = "AGENT_NAME", conflicts_with = "bundle")]
+    pub stdout: Option<String>,
+    /// Write all non-template agents into a single JSON file keyed by agent
+    /// name, .kiro/agents is not touched
+    #[arg(long, value_name = "FILE")]
+    pub bundle: Option<PathBuf>,
+}
+
+#[derive(clap::Args, Clone)]
+pub struct HookRunArgs {
+    /// Agent whose merged hooks are run
+    #[arg(value_name = "AGENT_NAME")]
+    pub agent: String,
+    /// Trigger of the hooks to run, e.g. agent-spawn or preToolUse
+    pub trigger: HookTrigger,
+    /// Only run the hook with this name
+    #[arg(long)]
+    pub name: Option<String>,
+    /// Tool name tested against the `matcher` of tool use hooks and passed
+    /// to them as tool_name
+    #[arg(long, value_name = "TOOL")]
+    pub tool: Option<String>,
+    /// JSON passed to tool use hooks as tool_input
+    #[arg(long, value_name = "JSON")]
+    pub tool_input: Option<String>,
+    #[arg(long, conflicts_with = "global")]
+    /// Ignore global $HOME kg.kdl and all global agent definitions
+    pub local: bool,
+    #[arg(short = 'g', long, conflicts_with = "local")]
+    /// Ignore local .kiro/generators/kg.kdl config agent definitions
+    pub global: bool,
+    /// Evaluate `when` blocks as if os, hostname or an environment variable
+    /// had this value, e.g. --assume os=linux --assume CI=true
+    #[arg(long, value_name = "KEY=VALUE")]
+    pub assume: Vec<String>,
+    /// Apply the patches of this `profile` block in kg.kdl after inheritance
+    #[arg(long, value_name = "NAME")]
+    pub profile: Option<String>,
+}
+
+#[derive(Subcommand, Clone)]
+pub enum HookCommand {
+    /// Run the merged hooks of an agent locally, like Kiro would, and print
+    /// their output, exit status and timing. Fails when a hook fails
+    Run(HookRunArgs),
 }
 
 #[derive(Subcommand, Clone)]
@@ -64,10 +164,17 @@ pub enum Command {
     /// Generate agent configuration JSON files, if local config found only
     /// local agents are generated. Use --global to generate $HOME agents
     #[command(alias = "g")]
-    Generate(Args),
+    Generate(GenerateArgs),
+    /// Validate for CI: print a short summary and exit with 0 when valid
+    /// and up to date, 1 on config errors, 3 on warnings and 4 when
+    /// generated files are missing or differ
+    Check(Args),
     /// Create default configuration in directory ~/.kiro/generators
     #[command()]
     Init(InitArgs),
+    /// Work with the hooks of an agent
+    #[command(subcommand)]
+    Hook(HookCommand),
     /// Display version information
     Version,
 }
@@ -83,6 +190,8 @@ impl Cli {
         match &self.format {
             OutputFormatArg::Table => OutputFormat::Table(self.color()),
             OutputFormatArg::Json => OutputFormat::Json,
+            OutputFormatArg::Sarif => OutputFormat::Sarif,
+            OutputFormatArg::Junit => OutputFormat::Junit,
         }
     }
 
@@ -99,25 +208,142 @@ impl Cli {
     }
 
     pub fn dry_run(&self) -> bool {
-        matches!(self.command, Command::Validate(_))
+        matches!(
+            self.command,
+            Command::Validate(_) | Command::Check(_) | Command::Hook(_)
+        )
     }
 
     pub fn is_local(&self) -> bool {
         match &self.command {
-            Command::Generate(args) => args.local,
-            Command::Validate(args) => args.local,
+            Command::Generate(g) => g.args.local,
+            Command::Validate(args) | Command::Check(args) => args.local,
+            Command::Hook(HookCommand::Run(args)) => args.local,
             _ => false,
         }
     }
 
     pub fn is_global(&self) -> bool {
         match &self.command {
-            Command::Generate(args) => args.global,
-            Command::Validate(args) => args.global,
+            Command::Generate(g) => g.args.global,
+            Command::Validate(args) | Command::Check(args) => args.global,
+            Command::Hook(HookCommand::Run(args)) => args.global,
+            _ => false,
+        }
+    }
+
+    /// `--output-dir`, made absolute so it survives changes of the working
+    /// directory (`--global` and project root discovery)
+    pub fn output_dir(&self) -> Option<PathBuf> {
+        match &self.command {
+            Command::Generate(GenerateArgs { args, .. })
+            | Command::Validate(args)
+            | Command::Check(args) => args
+                .output_dir
+                .as_ref()
+                .and_then(|d| std::path::absolute(d).ok()),
+            _ => None,
+        }
+    }
+
+    /// `--bundle`, made absolute like [`Cli::output_dir`]
+    pub fn bundle(&self) -> Option<PathBuf> {
+        match &self.command {
+            Command::Generate(GenerateArgs { bundle, .. }) => {
+                bundle.as_ref().and_then(|b| std::path::absolute(b).ok())
+            }
+            _ => None,
+        }
+    }
+
+    /// Agents selected by positional names and `--match` globs
+    pub fn agent_filter(&self) -> crate::Result<AgentFilter> {
+        match &self.command {
+            Command::Generate(GenerateArgs { args, .. })
+            | Command::Validate(args)
+            | Command::Check(args) => AgentFilter::new(args.agents.clone(), &args.matches),
+            _ => Ok(AgentFilter::default()),
+        }
+    }
+
+    /// `--profile` of validate, generate or hook run
+    pub fn profile(&self) -> Option<String> {
+        match &self.command {
+            Command::Generate(GenerateArgs { args, .. })
+            | Command::Validate(args)
+            | Command::Check(args) => args.profile.clone(),
+            Command::Hook(HookCommand::Run(args)) => args.profile.clone(),
+            _ => None,
+        }
+    }
+
+    /// `--drop-disabled-refs` of validate or generate
+    pub fn drop_disabled_refs(&self) -> bool {
+        match &self.command {
+            Command::Generate(GenerateArgs { args, .. })
+            | Command::Validate(args)
+            | Command::Check(args) => args.drop_disabled_refs,
+            _ => false,
+        }
+    }
+
+    /// `--check-resources` of validate or generate
+    pub fn check_resources(&self) -> bool {
+        match &self.command {
+            Command::Generate(GenerateArgs { args, .. })
+            | Command::Validate(args)
+            | Command::Check(args) => args.check_resources,
+            _ => false,
+        }
+    }
+
+    /// `--check-commands` of validate or generate
+    pub fn check_commands(&self) -> bool {
+        match &self.command {
+            Command::Generate(GenerateArgs { args, .. })
+            | Command::Validate(args)
+            | Command::Check(args) => args.check_commands,
             _ => false,
         }
     }
 
+    /// Host facts for `when` blocks, with `--assume` overrides
+    pub fn facts(&self) -> crate::Result<Facts> {
+        match &self.command {
+            Command::Generate(GenerateArgs { args, .. })
+            | Command::Validate(args)
+            | Command::Check(args) => Facts::host().assume(&args.assume),
+            Command::Hook(HookCommand::Run(args)) => Facts::host().assume(&args.assume),
+            _ => Ok(Facts::host()),
+        }
+    }
+
+    /// Organization wide config layers, lowest priority first. Relative
+    /// paths are made absolute like [`Cli::output_dir`]
+    pub fn base_layers(&self, fs: &crate::os::Fs) -> Vec<ConfigLayer> {
+        if self.is_local() {
+            return vec![];
+        }
+        if !self.config_path.is_empty() {
+            return self
+                .config_path
+                .iter()
+                .map(|p| ConfigLayer::new("config-path", absolute(p), false))
+                .collect();
+        }
+        if let Some(paths) = std::env::var_os(KG_CONFIG_PATH) {
+            return std::env::split_paths(&paths)
+                .filter(|p| !p.as_os_str().is_empty())
+                .map(|p| ConfigLayer::new(KG_CONFIG_PATH, absolute(&p), false))
+                .collect();
+        }
+        let system = ConfigLayer::new("system", SYSTEM_CONFIG_PATH, false);
+        if fs.exists(system.kg()) {
+            return vec![system];
+        }
+        vec![]
+    }
+
     /// Return home dir and ~/.kiro/generators/kg.kdl
     pub fn config(&self) -> crate::Result<(PathBuf, PathBuf)> {
         let home_dir = dirs::home_dir().ok_or(crate::format_err!("unable to find HOME dir"))?;
@@ -126,6 +352,11 @@ impl Cli {
     }
 }
 
+/// `path` made absolute against the working directory, as given if that fails
+fn absolute(path: &Path) -> PathBuf {
+    std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -137,12 +368,13 @@ mod tests {
             trace: None,
             color_override: ColorOverride::Auto,
             format: OutputFormatArg::Table,
+            config_path: vec![],
             command: Command::Validate(Args::default()),
         };
         assert!(cli.dry_run());
 
         let cli = Cli {
-            command: Command::Generate(Args::default()),
+            command: Command::Generate(GenerateArgs::default()),
             ..cli
         };
         assert!(!cli.dry_run());
@@ -155,6 +387,7 @@ mod tests {
             trace: None,
             color_override: ColorOverride::Auto,
             format: OutputFormatArg::Table,
+            config_path: vec![],
             command: Command::Validate(Args {
                 local: true,
                 ..Args::default()
@@ -171,9 +404,13 @@ mod tests {
             trace: None,
             color_override: ColorOverride::Auto,
             format: OutputFormatArg::Table,
-            command: Command::Generate(Args {
-                global: true,
-                ..Args::default()
+            config_path: vec![],
+            command: Command::Generate(GenerateArgs {
+                args: Args {
+                    global: true,
+                    ..Args::default()
+                },
+                ..GenerateArgs::default()
             }),
         };
         assert!(cli.is_global());
@@ -187,6 +424,7 @@ mod tests {
             trace: None,
             color_override: ColorOverride::Auto,
             format: OutputFormatArg::Table,
+            config_path: vec![],
             command: Command::default(),
         };
         // Color depends on terminal and env vars, just verify it doesn't panic
@@ -200,6 +438,7 @@ mod tests {
             trace: None,
             color_override: ColorOverride::Always,
             format: OutputFormatArg::Table,
+            config_path: vec![],
             command: Command::default(),
         };
         assert!(cli.color());
@@ -212,6 +451,7 @@ mod tests {
             trace: None,
             color_override: ColorOverride::Never,
             format: OutputFormatArg::Table,
+            config_path: vec![],
             command: Command::default(),
         };
         assert!(!cli.color());
@@ -224,6 +464,7 @@ mod tests {
             trace: None,
             color_override: ColorOverride::Always,
             format: OutputFormatArg::Table,
+            config_path: vec![],
             command: Command::default(),
         };
         assert!(matches!(cli.format_color(), OutputFormat::Table(true)));
@@ -242,6 +483,7 @@ mod tests {
             trace: None,
             color_override: ColorOverride::Auto,
             format: OutputFormatArg::Table,
+            config_path: vec![],
             command: Command::default(),
         };
         let result = cli.config();
@@ -250,4 +492,79 @@ mod tests {
         assert!(cfg.ends_with(".kiro/generators"));
         assert!(cfg.starts_with(&home));
     }
+
+    #[test]
+    fn test_output_dir() {
+        let cli = Cli {
+            debug: false,
+            trace: None,
+            color_override: ColorOverride::Auto,
+            format: OutputFormatArg::Table,
+            config_path: vec![],
+            command: Command::default(),
+        };
+        assert!(cli.output_dir().is_none());
+
+        let cli = Cli {
+            command: Command::Generate(GenerateArgs {
+                args: Args {
+                    output_dir: Some(PathBuf::from("build")),
+                    ..Args::default()
+                },
+                ..GenerateArgs::default()
+            }),
+            ..cli
+        };
+        let dir = cli.output_dir().unwrap();
+        assert!(dir.is_absolute());
+        assert!(dir.ends_with("build"));
+    }
+
+    #[test]
+    fn test_agent_filter() -> crate::Result<()> {
+        let cli = Cli::try_parse_from(["kg", "validate", "rust", "--match", "aws-*"]).unwrap();
+        let filter = cli.agent_filter()?;
+        assert!(filter.matches("rust"));
+        assert!(filter.matches("aws-test"));
+        assert!(!filter.matches("dependabot"));
+
+        let cli = Cli::try_parse_from(["kg", "generate"]).unwrap();
+        assert!(cli.agent_filter()?.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_base_layers() {
+        let fs = crate::os::Fs::new();
+        let cli = Cli::try_parse_from([
+            "kg",
+            "validate",
+            "--config-path",
+            "/org/kiro",
+            "--config-path",
+            "/team/kiro",
+        ])
+        .unwrap();
+        let layers = cli.base_layers(&fs);
+        assert_eq!(layers.len(), 2);
+        assert_eq!(layers[0].dir, PathBuf::from("/org/kiro"));
+        assert!(!layers[1].local);
+
+        let cli = Cli::try_parse_from(["kg", "validate", "--config-path", "org"]).unwrap();
+        let layers = cli.base_layers(&fs);
+        assert!(layers[0].dir.is_absolute());
+        assert!(layers[0].dir.ends_with("org"));
+
+        let cli =
+            Cli::try_parse_from(["kg", "validate", "--local", "--config-path", "/org"]).unwrap();
+        assert!(cli.base_layers(&fs).is_empty());
+    }
+
+    #[test]
+    fn test_facts() {
+        let cli = Cli::try_parse_from(["kg", "validate", "--assume", "os=plan9"]).unwrap();
+        assert!(cli.facts().is_ok());
+        let cli = Cli::try_parse_from(["kg", "validate", "--assume", "os"]).unwrap();
+        assert!(cli.facts().is_err());
+    }
 }
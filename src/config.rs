@@ -1,22 +1,35 @@
 mod agent;
 mod agent_file;
+mod condition;
 mod hook;
+mod lint;
+mod matrix;
 mod mcp;
 mod merge;
 mod native;
+mod param;
+mod profile;
+mod refs;
 
-pub use agent::{KdlAgent, KdlAgentDoc};
 use {
-    crate::Fs,
+    crate::{Fs, agent::BuiltInTool},
     facet::Facet,
     facet_kdl as kdl,
-    miette::IntoDiagnostic,
+    miette::{IntoDiagnostic, WrapErr},
     std::{
         collections::{HashMap, HashSet},
         fmt::Debug,
-        path::Path,
+        path::{Path, PathBuf},
     },
 };
+pub use {
+    agent::{KdlAgent, KdlAgentDoc},
+    condition::Facts,
+    lint::{UnknownFields, did_you_mean},
+    matrix::MatrixOrigin,
+    param::Params,
+    profile::Profile,
+};
 
 pub(crate) type ConfigResult<T> = miette::Result<T>;
 
@@ -25,6 +38,8 @@ pub(crate) type ConfigResult<T> = miette::Result<T>;
 pub(super) struct GenericItem {
     #[facet(kdl::argument)]
     pub item: String,
+    #[facet(kdl::property, default)]
+    pub merge: Option<String>,
 }
 
 #[derive(Facet, Debug, Default, PartialEq, Clone, Eq)]
@@ -32,6 +47,24 @@ pub(super) struct GenericItem {
 pub(super) struct GenericSet {
     #[facet(kdl::arguments)]
     pub item: HashSet<String>,
+    #[facet(kdl::property, default)]
+    pub merge: Option<String>,
+}
+
+/// Whether `merge` on `field` asks to replace the parent's values instead of
+/// the default union
+pub(super) fn replaces(field: &str, merge: Option<&str>) -> bool {
+    match merge {
+        None | Some("union") => false,
+        Some("replace") => true,
+        Some(other) => {
+            tracing::warn!(
+                target: crate::check::POLICY,
+                "unknown merge=\"{other}\" on {field}, expected \"union\" or \"replace\""
+            );
+            false
+        }
+    }
 }
 
 #[derive(Facet, Debug, Default, PartialEq, Clone, Eq)]
@@ -90,27 +123,48 @@ where
     if fs.exists(&path) {
         match fs.read_to_string_sync(&path).into_diagnostic() {
             Err(e) => Some(Err(e)),
-            Ok(content) => match kdl::from_str::<T>(&content) {
-                Err(e) => {
-                    let kdl_err =
-                        &crate::Error::DeserializeError(path.as_ref().display().to_string(), e);
-                    crate::output::print_error(kdl_err);
-                    Some(Err(crate::format_err!("{kdl_err}")))
-                }
-                Ok(r) => Some(Ok(r)),
-            },
+            Ok(content) => Some(kdl_parse_content(
+                &path.as_ref().display().to_string(),
+                &content,
+            )),
         }
     } else {
         None
     }
 }
 
+fn kdl_parse_content<T>(file: &str, content: &str) -> ConfigResult<T>
+where
+    T: for<'a> facet::Facet<'a>,
+{
+    let lint = lint::lint::<T>(file, content);
+    for deprecated in lint.deprecated {
+        tracing::warn!(target: crate::check::POLICY, file, line = deprecated.line, "{deprecated}");
+    }
+    if let Some(unknown) = lint.unknown {
+        let kdl_err = crate::Error::UnknownFields(unknown);
+        crate::output::print_error(&kdl_err);
+        return Err(kdl_err.into());
+    }
+    match kdl::from_str::<T>(&param::inherits_properties(content)) {
+        Err(e) => {
+            let kdl_err = crate::Error::DeserializeError(file.to_string(), e);
+            crate::output::print_error(&kdl_err);
+            Err(kdl_err.into())
+        }
+        Ok(r) => Ok(r),
+    }
+}
+
 #[cfg(test)]
 pub(crate) fn kdl_parse<T>(content: &str) -> ConfigResult<T>
 where
     T: for<'a> facet::Facet<'a>,
 {
-    match kdl::from_str::<T>(content) {
+    if let Some(unknown) = lint::lint::<T>("input.kdl", content).unknown {
+        return Err(crate::format_err!("{unknown}"));
+    }
+    match kdl::from_str::<T>(&param::inherits_properties(content)) {
         Err(e) => {
             print_error(&e);
             Err(crate::format_err!("{e}"))
@@ -119,15 +173,60 @@ where
     }
 }
 
+/// `builtin-tool "code" "code_search"`, a built-in tool of a Kiro release
+/// newer than the shipped catalog, followed by its aliases
+#[derive(facet::Facet, Clone, Debug, Default)]
+#[facet(default)]
+pub struct BuiltInToolDoc {
+    #[facet(facet_kdl::arguments)]
+    pub names: Vec<String>,
+}
+
+impl From<BuiltInToolDoc> for Option<BuiltInTool> {
+    fn from(value: BuiltInToolDoc) -> Self {
+        let mut names = value.names.into_iter();
+        names.next().map(|name| BuiltInTool {
+            name,
+            aliases: names.collect(),
+        })
+    }
+}
+
+/// `include "templates/*.kdl"`, a glob relative to the including file
+#[derive(facet::Facet, Clone, Debug)]
+pub struct IncludeDoc {
+    #[facet(facet_kdl::argument)]
+    pub path: String,
+}
+
 #[derive(facet::Facet, Default)]
+#[facet(rename_all = "kebab-case")]
 pub struct GeneratorConfigDoc {
     #[facet(facet_kdl::children, default)]
     pub agents: Vec<KdlAgentDoc>,
+    #[facet(facet_kdl::children, default)]
+    pub includes: Vec<IncludeDoc>,
+    #[facet(facet_kdl::child, default)]
+    pub output_dir: Option<String>,
+    #[facet(facet_kdl::children, default)]
+    pub profiles: Vec<profile::ProfileDoc>,
+    #[facet(facet_kdl::children, default)]
+    pub builtin_tools: Vec<BuiltInToolDoc>,
 }
 
 #[derive(Default)]
 pub struct GeneratorConfig {
     pub agents: HashMap<String, KdlAgent>,
+    /// Directory agents from this config are written to, instead of
+    /// `.kiro/agents`
+    pub output_dir: Option<PathBuf>,
+    /// File each agent is declared in, kg.kdl or an included file
+    pub files: HashMap<String, PathBuf>,
+    /// `agent-matrix` each expanded agent came from
+    pub matrix: HashMap<String, MatrixOrigin>,
+    pub profiles: HashMap<String, Profile>,
+    /// `builtin-tool` extensions of the tool catalog
+    pub builtin_tools: Vec<BuiltInTool>,
 }
 
 impl From<GeneratorConfigDoc> for GeneratorConfig {
@@ -136,7 +235,22 @@ impl From<GeneratorConfigDoc> for GeneratorConfig {
         for a in value.agents {
             agent.insert(a.name.clone(), a.into());
         }
-        Self { agents: agent }
+        let mut profiles: HashMap<String, Profile> = HashMap::new();
+        for p in value.profiles {
+            profiles.entry(p.name.clone()).or_default().extend(p.into());
+        }
+        Self {
+            agents: agent,
+            output_dir: value.output_dir.map(PathBuf::from),
+            files: HashMap::new(),
+            matrix: HashMap::new(),
+            profiles,
+            builtin_tools: value
+                .builtin_tools
+                .into_iter()
+                .filter_map(Option::from)
+                .collect(),
+        }
     }
 }
 
@@ -146,6 +260,45 @@ impl Debug for GeneratorConfig {
     }
 }
 
+impl GeneratorConfigDoc {
+    /// Parse a kg.kdl, then expand its `agent-matrix` nodes. The lint and
+    /// the deserializer run on the original lines, so their errors point at
+    /// the matrix rather than at the expanded agents. Returns the matrix each
+    /// expanded agent came from
+    pub fn from_path(
+        fs: &Fs,
+        path: impl AsRef<Path>,
+    ) -> Option<ConfigResult<(Self, HashMap<String, MatrixOrigin>)>> {
+        let path = path.as_ref();
+        if !fs.exists(path) {
+            return None;
+        }
+        let content = match fs.read_to_string_sync(path).into_diagnostic() {
+            Err(e) => return Some(Err(e)),
+            Ok(c) => c,
+        };
+        let file = path.display().to_string();
+        let Some(unexpanded) = matrix::unexpanded(&content) else {
+            return Some(kdl_parse_content(&file, &content).map(|doc| (doc, HashMap::new())));
+        };
+        Some(
+            kdl_parse_content::<Self>(&file, &unexpanded)
+                .and_then(|_| Self::expand(&file, &content)),
+        )
+    }
+
+    fn expand(file: &str, content: &str) -> ConfigResult<(Self, HashMap<String, MatrixOrigin>)> {
+        let (expanded, origins) = matrix::expand(content)
+            .wrap_err_with(|| format!("invalid agent-matrix in {file}"))?
+            .ok_or_else(|| crate::format_err!("{file} has no agent-matrix to expand"))?;
+        tracing::debug!("expanded {} matrix agents in {file}", origins.len());
+        match kdl::from_str::<Self>(&param::inherits_properties(&expanded)) {
+            Ok(doc) => Ok((doc, origins)),
+            Err(e) => Err(crate::Error::DeserializeError(file.to_string(), e).into()),
+        }
+    }
+}
+
 impl GeneratorConfig {
     pub fn get(&self, name: impl AsRef<str>) -> Option<&KdlAgent> {
         self.agents.get(name.as_ref())
@@ -292,6 +445,38 @@ mod tests {
         Ok(())
     }
 
+    #[test_log::test]
+    fn test_agent_unknown_nodes() -> ConfigResult<()> {
+        let kdl = r#"
+            agent "test" {
+                hook {
+                    stop "cleanup" {
+                        command "cleanup-script"
+                        cache-tl 5
+                    }
+                }
+            }
+        "#;
+        let Err(err) = kdl_parse::<GeneratorConfigDoc>(kdl) else {
+            panic!("cache-tl is not a hook node");
+        };
+        assert!(err.to_string().contains("cache-tl"));
+
+        let kdl = r#"
+            agent "test" {
+                hook {
+                    stop "cleanup" {
+                        cache-ttl 5
+                    }
+                }
+            }
+        "#;
+        let config = GeneratorConfig::from(kdl_parse::<GeneratorConfigDoc>(kdl)?);
+        let hook = &config.get("test").unwrap().hook.stop["cleanup"];
+        assert_eq!(hook.cache_ttl_seconds, 5);
+        Ok(())
+    }
+
     #[test_log::test]
     fn test_agent_file_source() -> ConfigResult<()> {
         let kdl_agent_file_source = r#"
@@ -364,6 +549,54 @@ mod tests {
         Ok(())
     }
 
+    #[test_log::test]
+    fn test_generator_config_nodes() -> ConfigResult<()> {
+        let kdl = r#"
+            output-dir "build"
+            include "templates/*.kdl"
+            agent "a" {}
+            include "more.kdl"
+            agent "b" {}
+        "#;
+        let doc: GeneratorConfigDoc = kdl_parse(kdl)?;
+        assert_eq!(doc.agents.len(), 2);
+        assert_eq!(doc.includes.len(), 2);
+        assert_eq!(doc.includes[0].path, "templates/*.kdl");
+        assert_eq!(doc.output_dir.as_deref(), Some("build"));
+        Ok(())
+    }
+
+    #[test_log::test]
+    fn test_deprecated_spelling() -> ConfigResult<()> {
+        let kdl = r#"
+            agent "git-readonly" template=#true {
+                native-tool {
+                    shell {
+                        allow "git status .*" "git log .*" \
+                              "git fetch .*" "git diff .*"
+                        override "git pull origin main"
+                    }
+                    aws {
+                        deny "s3:DeleteObject" "ec2:TerminateInstances"
+                    }
+                }
+            }
+        "#;
+        assert_eq!(
+            lint::lint::<GeneratorConfigDoc>("kg.kdl", kdl)
+                .deprecated
+                .len(),
+            3
+        );
+        let config = GeneratorConfig::from(kdl_parse::<GeneratorConfigDoc>(kdl)?);
+        let template = config.get("git-readonly").expect("git-readonly template");
+        assert!(template.is_template());
+        assert_eq!(template.get_tool_shell().allows.len(), 4);
+        assert_eq!(template.get_tool_shell().overrides.len(), 1);
+        assert_eq!(template.get_tool_aws().denies.len(), 2);
+        Ok(())
+    }
+
     #[test_log::test]
     fn test_tool_setting_invalid_json() -> ConfigResult<()> {
         let _kdl = r#"
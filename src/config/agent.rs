@@ -1,13 +1,15 @@
 use {
     super::{
         GenericItem,
+        condition::Condition,
         hook::{HookDoc, HookPart},
         mcp::CustomToolConfigDoc,
         native::{AwsTool, ExecuteShellTool, NativeTools, NativeToolsDoc, ReadTool, WriteTool},
+        param::{InheritsDoc, ParamDoc, Params},
     },
     crate::{
         agent::CustomToolConfig,
-        config::{GenericSet, GenericVec, split_newline},
+        config::{GenericSet, GenericVec, replaces, split_newline},
     },
     facet::Facet,
     facet_kdl as kdl,
@@ -66,18 +68,28 @@ pub struct KdlAgent {
     pub name: String,
     pub template: Option<bool>,
     pub description: Option<String>,
-    pub inherits: HashSet<String>,
+    /// Parents in declaration order, later parents win
+    pub inherits: Vec<String>,
+    /// Arguments passed to each parent, `inherits "aws-base" service="ec2"`
+    pub inherit_args: HashMap<String, Params>,
+    /// Declared parameters and their defaults, `param "service" default="s3"`
+    pub params: HashMap<String, Option<String>>,
     pub prompt: Option<String>,
     pub resources: HashSet<String>,
     pub include_mcp_json: Option<bool>,
     pub tools: HashSet<String>,
     pub allowed_tools: HashSet<String>,
     pub model: Option<String>,
+    pub output: Option<String>,
     pub hook: HookPart,
     pub mcp: HashMap<String, CustomToolConfig>,
     pub alias: HashMap<String, String>,
     pub native_tool: NativeTools,
     pub tool_setting: Vec<ToolSetting>,
+    /// `when` blocks, merged in by [`KdlAgent::evaluate`]
+    pub when: Vec<(Condition, KdlAgent)>,
+    /// Lists declared with `merge="replace"`, values of parents are dropped
+    pub replace: HashSet<String>,
 }
 
 #[derive(Facet, Clone, Default)]
@@ -92,8 +104,11 @@ pub struct KdlAgentDoc {
     #[facet(kdl::child, default)]
     pub(super) description: Option<String>,
 
-    #[facet(kdl::child, default)]
-    pub(super) inherits: GenericSet,
+    #[facet(kdl::children, default)]
+    pub(super) inherits: Vec<InheritsDoc>,
+
+    #[facet(kdl::children, default)]
+    pub(super) params: Vec<ParamDoc>,
 
     #[facet(kdl::child, default)]
     pub(super) prompt: Option<String>,
@@ -113,6 +128,9 @@ pub struct KdlAgentDoc {
     #[facet(kdl::child, default)]
     pub(super) model: Option<String>,
 
+    #[facet(kdl::child, default)]
+    pub(super) output: Option<String>,
+
     #[facet(kdl::child, default)]
     pub(super) hook: Option<HookDoc>,
 
@@ -127,6 +145,18 @@ pub struct KdlAgentDoc {
 
     #[facet(kdl::children, default)]
     pub(super) tool_setting: Vec<ToolSetting>,
+
+    /// `when os="linux" env="CI" hostname="build-*" { ... }`
+    #[facet(kdl::children, default)]
+    pub(super) when: Vec<KdlAgentDoc>,
+
+    // conditions of a `when` block, the lint rejects them on other nodes
+    #[facet(kdl::property, default)]
+    pub(super) os: Option<String>,
+    #[facet(kdl::property, default)]
+    pub(super) env: Option<String>,
+    #[facet(kdl::property, default)]
+    pub(super) hostname: Option<String>,
 }
 
 impl Debug for KdlAgent {
@@ -143,6 +173,7 @@ impl Display for KdlAgent {
 
 impl From<KdlAgentDoc> for KdlAgent {
     fn from(value: KdlAgentDoc) -> Self {
+        let replace = value.replace();
         Self {
             name: value.name.clone(),
             description: value.description.clone(),
@@ -150,15 +181,28 @@ impl From<KdlAgentDoc> for KdlAgent {
             alias: value.tool_aliases(),
             allowed_tools: value.allowed_tools(),
             inherits: value.inherits(),
+            inherit_args: value.inherit_args(),
+            params: value
+                .params
+                .iter()
+                .map(|p| (p.name.clone(), p.default.clone()))
+                .collect(),
             template: value.template,
             include_mcp_json: value.include_mcp_json,
             hook: value.hooks(),
             resources: value.resources(),
             model: value.model.clone(),
+            output: value.output.clone(),
             mcp: value.mcp_servers(),
             tools: value.tools(),
             tool_setting: Default::default(), // TODO use facet::Value
             native_tool: value.native_tool.into(),
+            when: value
+                .when
+                .into_iter()
+                .map(|w| (w.condition(), KdlAgent::from(w)))
+                .collect(),
+            replace,
         }
     }
 }
@@ -175,6 +219,13 @@ impl KdlAgent {
         self.template.is_some_and(|f| f)
     }
 
+    /// Resolve relative hook `script` files against `dir`, the directory of
+    /// the file this part of the agent was read from
+    pub fn scripts_relative_to(mut self, fs: &crate::Fs, dir: &std::path::Path) -> Self {
+        self.hook = self.hook.scripts_relative_to(fs, dir);
+        self
+    }
+
     pub fn get_tool_aws(&self) -> &AwsTool {
         &self.native_tool.aws
     }
@@ -193,6 +244,28 @@ impl KdlAgent {
 }
 
 impl KdlAgentDoc {
+    fn condition(&self) -> Condition {
+        Condition {
+            os: self.os.clone(),
+            env: self.env.clone(),
+            hostname: self.hostname.clone(),
+        }
+    }
+
+    /// Lists whose values replace, rather than extend, the parent's
+    fn replace(&self) -> HashSet<String> {
+        let resources = self.resources.iter().find_map(|r| r.merge.as_deref());
+        [
+            ("tools", self.tools.merge.as_deref()),
+            ("allowed-tools", self.allowed_tools.merge.as_deref()),
+            ("resources", resources),
+        ]
+        .into_iter()
+        .filter(|(field, merge)| replaces(field, *merge))
+        .map(|(field, _)| field.to_string())
+        .collect()
+    }
+
     pub fn tool_aliases(&self) -> HashMap<String, String> {
         let mut map: HashMap<String, String> = HashMap::new();
         for a in &self.alias {
@@ -214,8 +287,28 @@ impl KdlAgentDoc {
         self.tools.item.clone()
     }
 
-    pub fn inherits(&self) -> HashSet<String> {
-        self.inherits.item.clone()
+    pub fn inherits(&self) -> Vec<String> {
+        let mut inherits: Vec<String> = Vec::new();
+        for parent in self.inherits.iter().flat_map(|i| i.item.iter()) {
+            if !inherits.contains(parent) {
+                inherits.push(parent.clone());
+            }
+        }
+        inherits
+    }
+
+    pub fn inherit_args(&self) -> HashMap<String, Params> {
+        let mut map: HashMap<String, Params> = HashMap::new();
+        for i in &self.inherits {
+            let args = i.args();
+            if args.is_empty() {
+                continue;
+            }
+            for parent in &i.item {
+                map.entry(parent.clone()).or_default().extend(args.clone());
+            }
+        }
+        map
     }
 
     pub fn resources(&self) -> HashSet<String> {
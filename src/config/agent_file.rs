@@ -6,6 +6,7 @@ use {
         hook::HookDoc,
         mcp::CustomToolConfigDoc,
         native::NativeToolsDoc,
+        param::{InheritsDoc, ParamDoc},
     },
     crate::{
         Fs,
@@ -28,8 +29,11 @@ pub struct KdlAgentFileDoc {
     #[facet(kdl::child, default)]
     pub(super) description: Option<String>,
 
-    #[facet(kdl::child, default)]
-    pub(super) inherits: GenericSet,
+    #[facet(kdl::children, default)]
+    pub(super) inherits: Vec<InheritsDoc>,
+
+    #[facet(kdl::children, default)]
+    pub(super) params: Vec<ParamDoc>,
 
     #[facet(kdl::child, default)]
     pub(super) prompt: Option<String>,
@@ -49,6 +53,9 @@ pub struct KdlAgentFileDoc {
     #[facet(kdl::child, default)]
     pub(super) model: Option<String>,
 
+    #[facet(kdl::child, default)]
+    pub(super) output: Option<String>,
+
     #[facet(kdl::child, default)]
     pub(super) hook: Option<HookDoc>,
 
@@ -63,6 +70,9 @@ pub struct KdlAgentFileDoc {
 
     #[facet(kdl::children, default)]
     pub(super) tool_setting: Vec<ToolSetting>,
+
+    #[facet(kdl::children, default)]
+    pub(super) when: Vec<KdlAgentDoc>,
 }
 
 impl KdlAgentDoc {
@@ -86,17 +96,23 @@ impl KdlAgentDoc {
             description: file_source.description,
             template: None,
             inherits: file_source.inherits,
+            params: file_source.params,
             prompt: file_source.prompt,
             resources: file_source.resources,
             include_mcp_json: file_source.include_mcp_json,
             tools: file_source.tools,
             allowed_tools: file_source.allowed_tools,
             model: file_source.model,
+            output: file_source.output,
             hook: file_source.hook,
             mcp: file_source.mcp,
             alias: file_source.alias,
             native_tool: file_source.native_tool,
             tool_setting: file_source.tool_setting,
+            when: file_source.when,
+            os: None,
+            env: None,
+            hostname: None,
         }
     }
 }
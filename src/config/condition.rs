@@ -0,0 +1,207 @@
+use {super::KdlAgent, glob::Pattern, std::collections::HashMap};
+
+/// Facts `when` blocks are evaluated against. Defaults to the current host,
+/// individual facts can be overridden with `--assume key=value`
+#[derive(Clone, Debug, Default)]
+pub struct Facts {
+    os: String,
+    hostname: String,
+    /// Assumed environment variables, checked before the real environment
+    env: HashMap<String, String>,
+    /// Only use assumed environment variables
+    isolated: bool,
+}
+
+impl Facts {
+    pub fn host() -> Self {
+        Self {
+            os: std::env::consts::OS.to_string(),
+            hostname: gethostname::gethostname().to_string_lossy().to_string(),
+            env: HashMap::new(),
+            isolated: false,
+        }
+    }
+
+    /// Override facts with `key=value` pairs. `os` and `hostname` replace the
+    /// host values, any other key is treated as an environment variable
+    pub fn assume(
+        mut self,
+        assumptions: impl IntoIterator<Item = impl AsRef<str>>,
+    ) -> crate::Result<Self> {
+        for assumption in assumptions {
+            let assumption = assumption.as_ref();
+            let Some((key, value)) = assumption.split_once('=') else {
+                return Err(crate::format_err!(
+                    "invalid --assume '{assumption}', expected key=value"
+                ));
+            };
+            match key {
+                "os" => self.os = value.to_string(),
+                "hostname" => self.hostname = value.to_string(),
+                _ => {
+                    self.env.insert(key.to_string(), value.to_string());
+                }
+            }
+        }
+        Ok(self)
+    }
+
+    fn var(&self, name: &str) -> Option<String> {
+        match self.env.get(name) {
+            Some(v) => Some(v.clone()),
+            None if self.isolated => None,
+            None => std::env::var(name).ok(),
+        }
+    }
+}
+
+/// Properties of a `when` block, all given properties must match
+#[derive(Clone, Debug, Default)]
+pub struct Condition {
+    /// Glob matched against the OS, e.g. `linux`, `macos`
+    pub os: Option<String>,
+    /// `NAME` is set and not empty, or `NAME=glob` matches its value
+    pub env: Option<String>,
+    /// Glob matched against the hostname
+    pub hostname: Option<String>,
+}
+
+fn glob_matches(pattern: &str, value: &str) -> bool {
+    match Pattern::new(pattern) {
+        Ok(p) => p.matches(value),
+        Err(e) => {
+            tracing::warn!(target: crate::check::POLICY, "invalid pattern '{pattern}' in when block: {e}");
+            pattern == value
+        }
+    }
+}
+
+impl Condition {
+    pub fn is_empty(&self) -> bool {
+        self.os.is_none() && self.env.is_none() && self.hostname.is_none()
+    }
+
+    pub fn matches(&self, facts: &Facts) -> bool {
+        if let Some(os) = &self.os
+            && !glob_matches(os, &facts.os)
+        {
+            return false;
+        }
+        if let Some(hostname) = &self.hostname
+            && !glob_matches(hostname, &facts.hostname)
+        {
+            return false;
+        }
+        if let Some(env) = &self.env {
+            let matched = match env.split_once('=') {
+                Some((name, pattern)) => facts.var(name).is_some_and(|v| glob_matches(pattern, &v)),
+                None => facts.var(env).is_some_and(|v| !v.is_empty()),
+            };
+            if !matched {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+impl KdlAgent {
+    /// Merge the matching `when` blocks into the agent, as if they were
+    /// written inline. Values from a matching block win over the agent's own
+    pub fn evaluate(mut self, facts: &Facts) -> Self {
+        let blocks = std::mem::take(&mut self.when);
+        for (condition, block) in blocks {
+            if !condition.matches(facts) {
+                tracing::trace!("skipping when block {condition:?} for {}", self.name);
+                continue;
+            }
+            tracing::trace!("applying when block {condition:?} for {}", self.name);
+            let name = std::mem::take(&mut self.name);
+            let template = self.template;
+            self = block.evaluate(facts).merge(self);
+            self.name = name;
+            self.template = template;
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::config::{GeneratorConfig, GeneratorConfigDoc, kdl_parse},
+    };
+
+    fn facts(assume: &[&str]) -> Facts {
+        Facts {
+            isolated: true,
+            ..Facts::default()
+        }
+        .assume(assume)
+        .unwrap()
+    }
+
+    #[test]
+    fn test_condition_matches() {
+        let linux_ci = facts(&["os=linux", "hostname=build-42", "CI=true"]);
+        let cond = |os: Option<&str>, env: Option<&str>, hostname: Option<&str>| Condition {
+            os: os.map(String::from),
+            env: env.map(String::from),
+            hostname: hostname.map(String::from),
+        };
+        assert!(cond(None, None, None).matches(&linux_ci));
+        assert!(cond(Some("linux"), None, None).matches(&linux_ci));
+        assert!(!cond(Some("macos"), None, None).matches(&linux_ci));
+        assert!(cond(None, Some("CI"), Some("build-*")).matches(&linux_ci));
+        assert!(cond(None, Some("CI=t*"), None).matches(&linux_ci));
+        assert!(!cond(None, Some("CI=false"), None).matches(&linux_ci));
+        assert!(!cond(Some("linux"), Some("GITHUB_ACTIONS"), None).matches(&linux_ci));
+        assert!(!cond(None, None, Some("laptop")).matches(&linux_ci));
+
+        assert!(Facts::default().assume(["novalue"]).is_err());
+    }
+
+    #[test_log::test]
+    fn test_evaluate_when() -> crate::Result<()> {
+        let doc: GeneratorConfigDoc = kdl_parse(
+            r#"
+            agent "ci" {
+                model "laptop-model"
+                allowed-tools "read"
+                when os="linux" {
+                    model "linux-model"
+                    allowed-tools "shell"
+                    when env="CI" {
+                        allowed-tools "@cargo"
+                    }
+                }
+                when hostname="laptop-*" {
+                    allowed-tools "write"
+                }
+            }
+            "#,
+        )?;
+        let config = GeneratorConfig::from(doc);
+        let agent = config.agents.get("ci").unwrap();
+
+        let linux = agent.clone().evaluate(&facts(&["os=linux"]));
+        assert_eq!(linux.name, "ci");
+        assert_eq!(linux.model.as_deref(), Some("linux-model"));
+        assert!(linux.allowed_tools.contains("shell"));
+        assert!(!linux.allowed_tools.contains("@cargo"));
+        assert!(!linux.allowed_tools.contains("write"));
+
+        let ci = agent.clone().evaluate(&facts(&["os=linux", "CI=1"]));
+        assert!(ci.allowed_tools.contains("@cargo"));
+
+        let laptop = agent
+            .clone()
+            .evaluate(&facts(&["os=macos", "hostname=laptop-1"]));
+        assert_eq!(laptop.model.as_deref(), Some("laptop-model"));
+        assert!(laptop.allowed_tools.contains("write"));
+        assert!(laptop.allowed_tools.contains("read"));
+        assert!(laptop.when.is_empty());
+        Ok(())
+    }
+}
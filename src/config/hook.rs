@@ -1,10 +1,20 @@
 use {
-    crate::agent::hook::{Hook, HookTrigger},
+    crate::{
+        Fs,
+        agent::hook::{Hook, HookScript, HookTrigger},
+    },
     facet::Facet,
     facet_kdl as kdl,
-    std::collections::HashMap,
+    std::{ops::Index, path::Path},
 };
 
+/// A single word with a `/` or a script extension, `hooks/check.sh`
+fn looks_like_path(script: &str) -> bool {
+    let script = script.trim();
+    !script.contains(char::is_whitespace)
+        && (script.contains('/') || [".sh", ".bash", ".py"].iter().any(|e| script.ends_with(e)))
+}
+
 macro_rules! define_hook_doc {
     ($name:ident) => {
         #[derive(Facet, Default, Clone, Debug, PartialEq, Eq)]
@@ -12,25 +22,50 @@ macro_rules! define_hook_doc {
         pub struct $name {
             #[facet(kdl::argument)]
             pub name: String,
+            #[facet(kdl::property, default)]
+            disabled: bool,
+            #[facet(kdl::property, default)]
+            replace: bool,
+            #[facet(kdl::property, default)]
+            before: Option<String>,
+            #[facet(kdl::property, default)]
+            after: Option<String>,
             #[facet(kdl::child, default)]
             command: String,
             #[facet(kdl::child, default, rename = "timeout-ms")]
             timeout_ms: u64,
             #[facet(kdl::child, default, rename = "max-output-size")]
             max_output_size: u64,
-            #[facet(kdl::child, default, rename = "cache-ttl")]
+            #[facet(kdl::child, default, rename = "cache-ttl", alias = "cache-ttl-seconds")]
             cache_ttl_seconds: u64,
             #[facet(kdl::child, default)]
             matcher: Option<String>,
+            #[facet(kdl::child, default)]
+            script: Option<String>,
+            #[facet(kdl::child, default, rename = "script-file")]
+            script_file: Option<String>,
         }
         impl From<$name> for Hook {
             fn from(value: $name) -> Hook {
+                if value.script.is_some() && value.script_file.is_some() {
+                    tracing::warn!(
+                        target: crate::check::POLICY,
+                        "hook '{}' sets both script and script-file, script-file is ignored",
+                        value.name
+                    );
+                }
+                let file = value.script_file.map(|f| HookScript::File(f.into()));
                 Hook {
                     command: value.command,
                     timeout_ms: value.timeout_ms,
                     max_output_size: value.max_output_size,
                     cache_ttl_seconds: value.cache_ttl_seconds,
                     matcher: value.matcher,
+                    script: value.script.map(HookScript::Inline).or(file),
+                    disabled: value.disabled,
+                    replace: value.replace,
+                    before: value.before,
+                    after: value.after,
                 }
             }
         }
@@ -64,13 +99,153 @@ pub struct HookDoc {
     pub stop: Vec<HookStopDoc>,
 }
 
+/// Hooks of one trigger by name, in declaration order
+#[derive(Clone, Default, Debug, PartialEq, Eq)]
+pub struct NamedHooks(Vec<(String, Hook)>);
+
+impl NamedHooks {
+    pub fn get(&self, name: &str) -> Option<&Hook> {
+        self.iter().find(|(n, _)| *n == name).map(|(_, h)| h)
+    }
+
+    pub fn contains_key(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Hook)> {
+        self.0.iter().map(|(n, h)| (n, h))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&String, &mut Hook)> {
+        self.0.iter_mut().map(|(n, h)| (&*n, h))
+    }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut Hook> {
+        self.0.iter_mut().map(|(_, h)| h)
+    }
+
+    /// Add a hook, one with the same name is replaced in place
+    pub fn insert(&mut self, name: String, hook: Hook) {
+        match self.0.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, h)) => *h = hook,
+            None => self.0.push((name, hook)),
+        }
+    }
+
+    fn remove(&mut self, name: &str) -> Option<Hook> {
+        let i = self.0.iter().position(|(n, _)| n == name)?;
+        Some(self.0.remove(i).1)
+    }
+
+    /// Hooks a hook with `before` or `after` is placed next to, with `true`
+    /// for `after`. Targets that are unknown or the hook itself are left out
+    fn placements<'a>(&self, name: &str, hook: &'a Hook) -> Vec<(&'a str, bool)> {
+        [(&hook.before, false), (&hook.after, true)]
+            .into_iter()
+            .filter_map(|(target, after)| Some((target.as_deref()?, after)))
+            .filter(|(target, _)| *target != name && self.contains_key(target))
+            .collect()
+    }
+
+    /// Sort key of hook `i`: its declaration index, or for a placed hook the
+    /// key of its target followed by the side and its own index, so it
+    /// lands next to the target
+    fn key(&self, i: usize, seen: &mut Vec<usize>) -> Vec<i64> {
+        let (name, hook) = &self.0[i];
+        let own = i64::try_from(i).unwrap_or(i64::MAX);
+        let placed = self.placements(name, hook).into_iter().next();
+        let Some((target, after)) = placed.filter(|_| !seen.contains(&i)) else {
+            return vec![own, 0];
+        };
+        seen.push(i);
+        let target = self.0.iter().position(|(n, _)| n == target).unwrap_or(i);
+        let mut key = self.key(target, seen);
+        key.pop();
+        key.extend([if after { 1 } else { -1 }, own, 0]);
+        key
+    }
+
+    /// Indices in the order Kiro runs them, a topological sort of the
+    /// `before` and `after` edges that keeps declaration order otherwise,
+    /// and the hooks placed in a cycle
+    fn sorted(&self) -> (Vec<usize>, Vec<usize>) {
+        let n = self.0.len();
+        let mut edges = vec![Vec::new(); n];
+        let mut incoming = vec![0usize; n];
+        for (i, (name, hook)) in self.0.iter().enumerate() {
+            for (target, after) in self.placements(name, hook) {
+                let Some(t) = self.0.iter().position(|(n, _)| n == target) else {
+                    continue;
+                };
+                let (from, to) = if after { (t, i) } else { (i, t) };
+                edges[from].push(to);
+                incoming[to] += 1;
+            }
+        }
+        let keys: Vec<Vec<i64>> = (0..n).map(|i| self.key(i, &mut Vec::new())).collect();
+        let mut ready: Vec<usize> = (0..n).filter(|i| incoming[*i] == 0).collect();
+        let mut order = Vec::with_capacity(n);
+        while let Some(next) = (0..ready.len()).min_by(|a, b| keys[ready[*a]].cmp(&keys[ready[*b]]))
+        {
+            let i = ready.swap_remove(next);
+            order.push(i);
+            for &to in &edges[i] {
+                incoming[to] -= 1;
+                if incoming[to] == 0 {
+                    ready.push(to);
+                }
+            }
+        }
+        let mut cycle: Vec<usize> = (0..n).filter(|i| !order.contains(i)).collect();
+        cycle.sort_by(|a, b| keys[*a].cmp(&keys[*b]));
+        order.extend(&cycle);
+        (order, cycle)
+    }
+
+    /// Hooks in the order Kiro runs them, see [`NamedHooks::sorted`]
+    fn ordered(&self) -> Vec<(&str, &Hook)> {
+        self.sorted()
+            .0
+            .into_iter()
+            .map(|i| (self.0[i].0.as_str(), &self.0[i].1))
+            .collect()
+    }
+}
+
+impl FromIterator<(String, Hook)> for NamedHooks {
+    fn from_iter<T: IntoIterator<Item = (String, Hook)>>(iter: T) -> Self {
+        let mut hooks = Self::default();
+        for (name, hook) in iter {
+            hooks.insert(name, hook);
+        }
+        hooks
+    }
+}
+
+impl Index<&str> for NamedHooks {
+    type Output = Hook;
+
+    fn index(&self, name: &str) -> &Hook {
+        self.get(name)
+            .unwrap_or_else(|| panic!("no hook named '{name}'"))
+    }
+}
+
 #[derive(Clone, Default, Debug, PartialEq, Eq)]
 pub struct HookPart {
-    pub agent_spawn: HashMap<String, Hook>,
-    pub user_prompt_submit: HashMap<String, Hook>,
-    pub pre_tool_use: HashMap<String, Hook>,
-    pub post_tool_use: HashMap<String, Hook>,
-    pub stop: HashMap<String, Hook>,
+    pub agent_spawn: NamedHooks,
+    pub user_prompt_submit: NamedHooks,
+    pub pre_tool_use: NamedHooks,
+    pub post_tool_use: NamedHooks,
+    pub stop: NamedHooks,
 }
 
 impl From<HookDoc> for HookPart {
@@ -106,16 +281,113 @@ impl From<HookDoc> for HookPart {
 }
 
 impl HookPart {
-    pub fn hooks(&self, trigger: &HookTrigger) -> Vec<Hook> {
+    fn trigger(&self, trigger: &HookTrigger) -> &NamedHooks {
+        match trigger {
+            HookTrigger::AgentSpawn => &self.agent_spawn,
+            HookTrigger::UserPromptSubmit => &self.user_prompt_submit,
+            HookTrigger::PreToolUse => &self.pre_tool_use,
+            HookTrigger::PostToolUse => &self.post_tool_use,
+            HookTrigger::Stop => &self.stop,
+        }
+    }
+
+    pub fn hooks_mut(&mut self, trigger: &HookTrigger) -> &mut NamedHooks {
         match trigger {
-            HookTrigger::AgentSpawn => self.agent_spawn.values().cloned().collect(),
-            HookTrigger::UserPromptSubmit => self.user_prompt_submit.values().cloned().collect(),
-            HookTrigger::PreToolUse => self.pre_tool_use.values().cloned().collect(),
-            HookTrigger::PostToolUse => self.post_tool_use.values().cloned().collect(),
-            HookTrigger::Stop => self.stop.values().cloned().collect(),
+            HookTrigger::AgentSpawn => &mut self.agent_spawn,
+            HookTrigger::UserPromptSubmit => &mut self.user_prompt_submit,
+            HookTrigger::PreToolUse => &mut self.pre_tool_use,
+            HookTrigger::PostToolUse => &mut self.post_tool_use,
+            HookTrigger::Stop => &mut self.stop,
         }
     }
 
+    /// Resolve relative `script` files against `dir`, the directory of the
+    /// file declaring them. A single-line `script` naming a file in `fs` is
+    /// that file, otherwise the script itself
+    pub fn scripts_relative_to(mut self, fs: &Fs, dir: &Path) -> Self {
+        let resolve = |path: &Path| match path.is_relative() && !path.starts_with("~") {
+            true => dir.join(path),
+            false => path.to_path_buf(),
+        };
+        for trigger in enum_iterator::all::<HookTrigger>() {
+            for (name, hook) in self.hooks_mut(&trigger).iter_mut() {
+                match &hook.script {
+                    Some(HookScript::File(path)) => {
+                        hook.script = Some(HookScript::File(resolve(path)));
+                    }
+                    Some(HookScript::Inline(script)) if !script.trim().contains('\n') => {
+                        let path = resolve(Path::new(script.trim()));
+                        let home = path
+                            .strip_prefix("~")
+                            .ok()
+                            .zip(dirs::home_dir())
+                            .map(|(rest, home)| home.join(rest));
+                        if fs.exists(home.as_ref().unwrap_or(&path)) {
+                            hook.script = Some(HookScript::File(path));
+                        } else if looks_like_path(script) {
+                            tracing::warn!(
+                                target: crate::check::POLICY,
+                                "{trigger} hook '{name}' script '{script}' is not a file, it is \
+                                 written as the script itself"
+                            );
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        self
+    }
+
+    /// Enabled hooks of `trigger` in the order Kiro runs them
+    pub fn hooks(&self, trigger: &HookTrigger) -> Vec<Hook> {
+        self.trigger(trigger)
+            .ordered()
+            .into_iter()
+            .filter(|(_, h)| !h.disabled)
+            .map(|(_, h)| h.clone())
+            .collect()
+    }
+
+    /// Hooks of `trigger` with their names in the order Kiro runs them,
+    /// disabled ones included
+    pub fn named(&self, trigger: &HookTrigger) -> Vec<(&str, &Hook)> {
+        self.trigger(trigger).ordered()
+    }
+
+    /// Hooks whose `before` or `after` names no hook of their trigger, with
+    /// that name
+    pub fn misplaced(&self) -> Vec<(HookTrigger, &str, &str)> {
+        let mut misplaced = Vec::new();
+        for trigger in enum_iterator::all::<HookTrigger>() {
+            let hooks = self.trigger(&trigger);
+            for (name, hook) in hooks.iter() {
+                for target in [&hook.before, &hook.after].into_iter().flatten() {
+                    if target == name || !hooks.contains_key(target) {
+                        misplaced.push((trigger, name.as_str(), target.as_str()));
+                    }
+                }
+            }
+        }
+        misplaced
+    }
+
+    /// Hooks whose `before` and `after` contradict each other, per trigger
+    pub fn cycles(&self) -> Vec<(HookTrigger, Vec<&str>)> {
+        enum_iterator::all::<HookTrigger>()
+            .filter_map(|trigger| {
+                let hooks = self.trigger(&trigger);
+                let cycle = hooks.sorted().1;
+                (!cycle.is_empty()).then(|| {
+                    (
+                        trigger,
+                        cycle.into_iter().map(|i| hooks.0[i].0.as_str()).collect(),
+                    )
+                })
+            })
+            .collect()
+    }
+
     pub fn merge(mut self, other: Self) -> Self {
         self.agent_spawn = merge_hooks(self.agent_spawn, other.agent_spawn);
         self.user_prompt_submit = merge_hooks(self.user_prompt_submit, other.user_prompt_submit);
@@ -126,23 +398,19 @@ impl HookPart {
     }
 }
 
-fn merge_hooks(
-    mut base: HashMap<String, Hook>,
-    other: HashMap<String, Hook>,
-) -> HashMap<String, Hook> {
-    if base.is_empty() {
-        return other;
-    }
-    if other.is_empty() {
-        return base;
-    }
-
-    for (key, other_hook) in other {
-        base.entry(key)
-            .and_modify(|h| *h = h.clone().merge(other_hook.clone()))
-            .or_insert(other_hook);
+/// Parent hooks first, merged with the child's hook of the same name, then
+/// the child's own hooks, each in declaration order
+fn merge_hooks(mut base: NamedHooks, other: NamedHooks) -> NamedHooks {
+    let mut merged = NamedHooks::default();
+    for (name, parent) in other.0 {
+        let hook = match base.remove(&name) {
+            Some(child) => child.merge(parent),
+            None => parent,
+        };
+        merged.0.push((name, hook));
     }
-    base
+    merged.0.extend(base.0);
+    merged
 }
 
 #[cfg(test)]
@@ -153,20 +421,25 @@ mod tests {
         std::time::Duration,
     };
 
-    fn rando() -> HashMap<String, Hook> {
+    fn rando() -> NamedHooks {
         let value = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap()
             .as_secs();
 
         let name = format!("$name-{value}");
-        let mut hooks = HashMap::new();
+        let mut hooks = NamedHooks::default();
         hooks.insert(name, Hook {
             command: format!("{value}"),
             timeout_ms: value,
             max_output_size: value,
             cache_ttl_seconds: value,
             matcher: Some(format!("{value}")),
+            script: None,
+            disabled: false,
+            replace: false,
+            before: None,
+            after: None,
         });
         hooks
     }
@@ -235,6 +508,117 @@ mod tests {
         Ok(())
     }
 
+    #[test_log::test]
+    pub fn test_hooks_disabled_replace() -> Result<()> {
+        let parent: HookDoc = kdl_parse(
+            r#"
+            agent-spawn "sts" {
+                command "aws sts get-caller-identity"
+            }
+            stop "report" {
+                command "report.sh"
+                timeout-ms 5000
+                matcher "ignored"
+            }
+        "#,
+        )?;
+        let child: HookDoc = kdl_parse(
+            r#"
+            agent-spawn "sts" disabled=#true
+            stop "report" replace=#true {
+                command "summary.sh"
+            }
+        "#,
+        )?;
+        let merged = HookPart::from(child).merge(HookPart::from(parent));
+
+        let sts = &merged.agent_spawn["sts"];
+        assert!(sts.disabled);
+        assert_eq!(sts.command, "aws sts get-caller-identity");
+        assert!(merged.hooks(&HookTrigger::AgentSpawn).is_empty());
+
+        let report = &merged.stop["report"];
+        assert_eq!(report.command, "summary.sh");
+        assert_eq!(report.timeout_ms, 0);
+        assert_eq!(report.matcher, None);
+        assert_eq!(merged.hooks(&HookTrigger::Stop).len(), 1);
+        Ok(())
+    }
+
+    #[test_log::test]
+    pub fn test_hooks_order() -> Result<()> {
+        let parent: HookDoc = kdl_parse(
+            r#"
+            agent-spawn "status" { command "git status"; }
+            agent-spawn "log" { command "git log -1"; }
+            agent-spawn "fetch" { command "git fetch"; }
+        "#,
+        )?;
+        let child: HookDoc = kdl_parse(
+            r#"
+            agent-spawn "whoami" { command "whoami"; }
+            agent-spawn "cd" before="status" { command "cd repo"; }
+            agent-spawn "log" { timeout-ms 100; }
+            agent-spawn "date" after="nowhere" { command "date"; }
+        "#,
+        )?;
+        let merged = HookPart::from(child).merge(HookPart::from(parent));
+        let order: Vec<String> = merged
+            .hooks(&HookTrigger::AgentSpawn)
+            .into_iter()
+            .map(|h| h.command)
+            .collect();
+        assert_eq!(order, vec![
+            "cd repo",
+            "git status",
+            "git log -1",
+            "git fetch",
+            "whoami",
+            "date"
+        ]);
+        assert_eq!(merged.misplaced(), vec![(
+            HookTrigger::AgentSpawn,
+            "date",
+            "nowhere"
+        )]);
+        assert!(merged.cycles().is_empty());
+        Ok(())
+    }
+
+    #[test_log::test]
+    pub fn test_hooks_order_chain() -> Result<()> {
+        let order = |kdl: &str| -> Result<(Vec<String>, HookPart)> {
+            let hooks = HookPart::from(kdl_parse::<HookDoc>(kdl)?);
+            let names = hooks
+                .named(&HookTrigger::Stop)
+                .into_iter()
+                .map(|(n, _)| n.to_string())
+                .collect();
+            Ok((names, hooks))
+        };
+        let (names, hooks) = order(
+            r#"
+            stop "x" after="y" { command "x"; }
+            stop "y" after="z" { command "y"; }
+            stop "z" { command "z"; }
+            stop "w" before="x" { command "w"; }
+        "#,
+        )?;
+        assert_eq!(names, vec!["z", "y", "w", "x"]);
+        assert!(hooks.cycles().is_empty());
+
+        let (names, hooks) = order(
+            r#"
+            stop "a" before="b" { command "a"; }
+            stop "b" before="a" { command "b"; }
+            stop "c" { command "c"; }
+        "#,
+        )?;
+        assert_eq!(names, vec!["c", "a", "b"]);
+        assert_eq!(hooks.cycles(), vec![(HookTrigger::Stop, vec!["a", "b"])]);
+        Ok(())
+    }
+
     #[test_log::test]
     pub fn test_hooks_empty() -> Result<()> {
         let child = HookPart::default();
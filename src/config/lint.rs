@@ -0,0 +1,548 @@
+use {
+    facet::{Def, Facet, Field, Shape, Type, UserType},
+    miette::{Diagnostic, NamedSource, SourceSpan},
+    std::fmt::{self, Display},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldKind {
+    Node,
+    Property,
+}
+
+impl Display for FieldKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Node => write!(f, "node"),
+            Self::Property => write!(f, "property"),
+        }
+    }
+}
+
+/// A node or property in a KDL document that does not map to any known field
+#[derive(Debug, Clone, thiserror::Error, Diagnostic)]
+#[error("unknown {kind} `{name}` in {parent}")]
+pub struct UnknownField {
+    pub name: String,
+    pub kind: FieldKind,
+    pub parent: String,
+    pub suggestion: Option<String>,
+    #[help]
+    help: Option<String>,
+    #[label("unknown {kind}")]
+    pub span: SourceSpan,
+}
+
+/// All unknown fields found in a single KDL file
+#[derive(Debug, thiserror::Error, Diagnostic)]
+#[error("{}", self.summary())]
+#[diagnostic(help("run with --debug to see every config file kg reads"))]
+pub struct UnknownFields {
+    pub file: String,
+    #[source_code]
+    source_code: NamedSource<String>,
+    #[related]
+    pub fields: Vec<UnknownField>,
+}
+
+impl UnknownFields {
+    fn summary(&self) -> String {
+        let fields: Vec<String> = self
+            .fields
+            .iter()
+            .map(|f| match &f.suggestion {
+                Some(s) => format!("{f}, did you mean `{s}`?"),
+                None => f.to_string(),
+            })
+            .collect();
+        format!(
+            "invalid config in file {}: {}",
+            self.file,
+            fields.join("; ")
+        )
+    }
+}
+
+/// A node spelled with an alias that is still accepted but no longer
+/// preferred, e.g. `allow` instead of `allows`
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("`{name}` in {parent} is deprecated, use `{replacement}`")]
+pub struct DeprecatedField {
+    pub name: String,
+    pub replacement: String,
+    pub parent: String,
+    pub line: usize,
+    pub span: SourceSpan,
+}
+
+#[derive(Default)]
+struct Found {
+    unknown: Vec<UnknownField>,
+    deprecated: Vec<DeprecatedField>,
+}
+
+/// Result of walking a KDL document against the shape it deserializes into
+#[derive(Debug, Default)]
+pub struct Lint {
+    pub unknown: Option<UnknownFields>,
+    pub deprecated: Vec<DeprecatedField>,
+}
+
+/// Edit distance between two names, counting a transposition of adjacent
+/// characters as a single edit
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+/// Closest candidate to `name`, if any is near enough to be a plausible typo.
+/// Ties are resolved in favour of the earliest candidate.
+pub fn did_you_mean<'a>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Option<&'a str> {
+    let threshold = (name.chars().count() / 3).max(1);
+    let mut best: Option<(usize, &'a str)> = None;
+    for candidate in candidates {
+        let distance = edit_distance(name, candidate);
+        if distance <= threshold && best.is_none_or(|(d, _)| distance < d) {
+            best = Some((distance, candidate));
+        }
+    }
+    best.map(|(_, c)| c)
+}
+
+/// Properties of a shape shared by several nodes that only one of them takes:
+/// a `when` block has the body of an agent, but only `when` has conditions
+const NODE_PROPERTIES: &[(&str, &[&str])] = &[("when", &["os", "env", "hostname"])];
+
+/// Nodes whose properties are arguments rather than fields of their shape,
+/// `inherits "aws-base" service="ec2"`
+const ANY_PROPERTY: &[&str] = &["inherits"];
+
+/// Whether a node called `node` takes the property `name` of its shape
+fn takes_property(node: &str, name: &str) -> bool {
+    NODE_PROPERTIES
+        .iter()
+        .all(|(owner, properties)| *owner == node || !properties.contains(&name))
+}
+
+fn is_kdl(field: &Field, attr: &str) -> bool {
+    field.has_attr(Some("kdl"), attr)
+}
+
+/// Unwrap `Option`, `Vec` and sets down to the shape a KDL node deserializes
+/// into
+fn element(shape: &'static Shape) -> &'static Shape {
+    match shape.def {
+        Def::Option(o) => element(o.t),
+        Def::List(l) => element(l.t()),
+        Def::Set(s) => element(s.t),
+        _ => shape,
+    }
+}
+
+fn fields(shape: &'static Shape) -> &'static [Field] {
+    match element(shape).ty {
+        Type::User(UserType::Struct(s)) => s.fields,
+        _ => &[],
+    }
+}
+
+/// Node names accepted by `field`, most common spelling first
+fn node_names(field: &Field) -> Vec<String> {
+    let mut names = Vec::with_capacity(3);
+    if is_kdl(field, "children") {
+        let singular = facet_singularize::singularize(field.name);
+        if singular != field.name {
+            names.push(singular);
+        }
+        names.push(field.name.to_string());
+    } else if is_kdl(field, "child") {
+        names.push(field.name.to_string());
+    }
+    if !names.is_empty()
+        && let Some(alias) = field.alias
+    {
+        names.push(alias.to_string());
+    }
+    names
+}
+
+fn line_of(content: &str, span: SourceSpan) -> usize {
+    content
+        .get(..span.offset())
+        .map(|c| c.matches('\n').count() + 1)
+        .unwrap_or_default()
+}
+
+fn accepts_node(field: &Field, name: &str) -> bool {
+    if is_kdl(field, "children") {
+        facet_singularize::is_singular_of(name, field.name) || field.alias == Some(name)
+    } else if is_kdl(field, "child") {
+        field.name == name || field.alias == Some(name)
+    } else {
+        false
+    }
+}
+
+fn accepts_property(field: &Field, name: &str) -> bool {
+    is_kdl(field, "property") && (field.name == name || field.alias == Some(name))
+}
+
+fn describe(node: &::kdl::KdlNode) -> String {
+    let name = node.name().value();
+    match node
+        .entries()
+        .iter()
+        .find(|e| e.name().is_none())
+        .and_then(|e| e.value().as_string())
+    {
+        Some(arg) => format!("`{name} \"{arg}\"`"),
+        None => format!("`{name}`"),
+    }
+}
+
+fn unknown(
+    name: &str,
+    kind: FieldKind,
+    parent: &str,
+    candidates: Vec<String>,
+    span: SourceSpan,
+) -> UnknownField {
+    let suggestion = did_you_mean(name, candidates.iter().map(String::as_str)).map(String::from);
+    let help = match &suggestion {
+        Some(s) => Some(format!("did you mean `{s}`?")),
+        None if candidates.is_empty() => Some(format!("{parent} does not accept any {kind}")),
+        None => Some(format!("expected one of: {}", candidates.join(", "))),
+    };
+    UnknownField {
+        name: name.to_string(),
+        kind,
+        parent: parent.to_string(),
+        suggestion,
+        help,
+        span,
+    }
+}
+
+fn check_node(shape: &'static Shape, node: &::kdl::KdlNode, content: &str, found: &mut Found) {
+    let parent = describe(node);
+    let fields = fields(shape);
+    let any_property = ANY_PROPERTY.contains(&node.name().value());
+    for entry in node.entries() {
+        let Some(key) = entry.name().filter(|_| !any_property) else {
+            continue;
+        };
+        let takes = |f: &&Field| takes_property(node.name().value(), f.name);
+        if !fields
+            .iter()
+            .filter(takes)
+            .any(|f| accepts_property(f, key.value()))
+        {
+            let candidates = fields
+                .iter()
+                .filter(takes)
+                .filter(|f| is_kdl(f, "property"))
+                .map(|f| f.name.to_string())
+                .collect();
+            found.unknown.push(unknown(
+                key.value(),
+                FieldKind::Property,
+                &parent,
+                candidates,
+                key.span(),
+            ));
+        }
+    }
+    if let Some(children) = node.children() {
+        check_children(shape, &parent, children.nodes(), content, found);
+    }
+}
+
+fn check_children(
+    shape: &'static Shape,
+    parent: &str,
+    nodes: &[::kdl::KdlNode],
+    content: &str,
+    found: &mut Found,
+) {
+    let fields = fields(shape);
+    for node in nodes {
+        let name = node.name().value();
+        match fields.iter().find(|f| accepts_node(f, name)) {
+            Some(field) => {
+                if field.alias == Some(name) {
+                    let span = node.name().span();
+                    found.deprecated.push(DeprecatedField {
+                        name: name.to_string(),
+                        replacement: field.name.to_string(),
+                        parent: parent.to_string(),
+                        line: line_of(content, span),
+                        span,
+                    });
+                }
+                check_node(field.shape(), node, content, found)
+            }
+            None => {
+                let candidates = fields.iter().flat_map(node_names).collect();
+                found.unknown.push(unknown(
+                    name,
+                    FieldKind::Node,
+                    parent,
+                    candidates,
+                    node.name().span(),
+                ));
+            }
+        }
+    }
+}
+
+/// Walk a KDL document against the shape of `T` and report every node or
+/// property that `T` would silently ignore, as well as nodes spelled with a
+/// deprecated alias.
+///
+/// Syntax errors are not reported here, they are left to
+/// [`facet_kdl::from_str`] which renders them with full context.
+pub fn lint<T>(file: impl AsRef<str>, content: &str) -> Lint
+where
+    T: for<'a> Facet<'a>,
+{
+    let Ok(doc) = content.parse::<::kdl::KdlDocument>() else {
+        return Lint::default();
+    };
+    let mut found = Found::default();
+    check_children(T::SHAPE, "top level", doc.nodes(), content, &mut found);
+    let unknown = (!found.unknown.is_empty()).then(|| UnknownFields {
+        file: file.as_ref().to_string(),
+        source_code: NamedSource::new(file.as_ref(), content.to_string()),
+        fields: found.unknown,
+    });
+    Lint {
+        unknown,
+        deprecated: found.deprecated,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::config::{
+            GeneratorConfig,
+            GeneratorConfigDoc,
+            agent_file::KdlAgentFileDoc,
+            kdl_parse,
+        },
+    };
+
+    fn names(found: &UnknownFields) -> Vec<(&str, Option<&str>)> {
+        found
+            .fields
+            .iter()
+            .map(|f| (f.name.as_str(), f.suggestion.as_deref()))
+            .collect()
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("", ""), 0);
+        assert_eq!(edit_distance("shell", "shell"), 0);
+        assert_eq!(edit_distance("shel", "shell"), 1);
+        assert_eq!(edit_distance("allowed-tool", "allowed-tools"), 1);
+        assert_eq!(edit_distance("wirte", "write"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn test_did_you_mean() {
+        let candidates = ["shell", "aws", "read", "write"];
+        assert_eq!(did_you_mean("shel", candidates), Some("shell"));
+        assert_eq!(did_you_mean("wirte", candidates), Some("write"));
+        assert_eq!(did_you_mean("kubernetes", candidates), None);
+    }
+
+    #[test_log::test]
+    fn test_unknown_inline_agent_fields() {
+        let kdl = indoc::indoc! {r#"
+            agent "test" templat=#true {
+                allowed-tool "read"
+                resources "file://README.md"
+                native-tool {
+                    shel {
+                        allows "ls"
+                    }
+                }
+                hook {
+                    agent-spwan "x" {
+                        command "echo"
+                    }
+                }
+                mcp "docs" {
+                    comand "docs-mcp"
+                }
+            }
+        "#};
+        let found = lint::<GeneratorConfigDoc>("kg.kdl", kdl)
+            .unknown
+            .expect("unknown fields");
+        assert_eq!(names(&found), vec![
+            ("templat", Some("template")),
+            ("allowed-tool", Some("allowed-tools")),
+            ("shel", Some("shell")),
+            ("agent-spwan", Some("agent-spawn")),
+            ("comand", Some("command")),
+        ]);
+        assert_eq!(found.fields[0].kind, FieldKind::Property);
+        assert_eq!(found.fields[2].parent, "`native-tool`");
+        let msg = found.to_string();
+        assert!(msg.contains("kg.kdl"));
+        assert!(msg.contains("did you mean `allowed-tools`?"));
+    }
+
+    #[test_log::test]
+    fn test_unknown_top_level() {
+        let found = lint::<GeneratorConfigDoc>("kg.kdl", r#"agnet "test" {}"#)
+            .unknown
+            .expect("unknown fields");
+        assert_eq!(names(&found), vec![("agnet", Some("agent"))]);
+    }
+
+    #[test_log::test]
+    fn test_unknown_file_fields() {
+        let kdl = indoc::indoc! {r#"
+            descripion "typo"
+            native-tool {
+                kubernetes {}
+            }
+        "#};
+        let found = lint::<KdlAgentFileDoc>("agent.kdl", kdl)
+            .unknown
+            .expect("unknown fields");
+        assert_eq!(names(&found), vec![
+            ("descripion", Some("description")),
+            ("kubernetes", None)
+        ]);
+        assert!(
+            found.fields[1]
+                .help
+                .as_deref()
+                .unwrap_or_default()
+                .contains("shell")
+        );
+    }
+
+    #[test_log::test]
+    fn test_when_properties() {
+        let kdl = indoc::indoc! {r#"
+            agent "test" os="linux" {
+                when os="linux" env="CI" {
+                    when hostname="build-*" {}
+                }
+            }
+        "#};
+        let found = lint::<GeneratorConfigDoc>("kg.kdl", kdl)
+            .unknown
+            .expect("unknown fields");
+        assert_eq!(names(&found), vec![("os", None)]);
+        assert_eq!(
+            found.fields[0].help.as_deref(),
+            Some("expected one of: template, include-mcp-json")
+        );
+    }
+
+    #[test_log::test]
+    fn test_inherits_properties() -> crate::Result<()> {
+        let kdl = indoc::indoc! {r#"
+            agent "aws-base" template=#true {
+                param "service"
+                description "{service}"
+            }
+            agent "ec2" {
+                inherits "aws-base" service="ec2"
+            }
+        "#};
+        assert!(lint::<GeneratorConfigDoc>("kg.kdl", kdl).unknown.is_none());
+        let config = GeneratorConfig::from(kdl_parse::<GeneratorConfigDoc>(kdl)?);
+        let args = &config.get("ec2").expect("ec2").inherit_args["aws-base"];
+        let base = config.get("aws-base").expect("aws-base");
+        let values = base.bind(Some("ec2"), args)?;
+        let resolved = base.clone().substitute(&values);
+        assert_eq!(resolved.description.as_deref(), Some("ec2"));
+        Ok(())
+    }
+
+    #[test_log::test]
+    fn test_known_fields() {
+        let kdl = indoc::indoc! {r#"
+            agent "test" template=#true include-mcp-json=#true {
+                resource "file://README.md"
+                alias "execute_bash" "shell"
+                hook {
+                    agent-spawn "x" {
+                        cache-ttl-seconds 5
+                    }
+                }
+            }
+        "#};
+        let found = lint::<GeneratorConfigDoc>("kg.kdl", kdl);
+        assert!(found.unknown.is_none());
+        assert_eq!(found.deprecated.len(), 1);
+        assert!(
+            lint::<GeneratorConfigDoc>("kg.kdl", "agent {")
+                .unknown
+                .is_none()
+        );
+    }
+
+    #[test_log::test]
+    fn test_deprecated_fields() {
+        let kdl = indoc::indoc! {r#"
+            agent "test" {
+                native-tool {
+                    shell {
+                        allow "ls"
+                        allows "pwd"
+                        deny "rm .*"
+                        override "git push .*"
+                    }
+                }
+            }
+        "#};
+        let found = lint::<GeneratorConfigDoc>("kg.kdl", kdl);
+        assert!(found.unknown.is_none());
+        let deprecated: Vec<(&str, &str, usize)> = found
+            .deprecated
+            .iter()
+            .map(|d| (d.name.as_str(), d.replacement.as_str(), d.line))
+            .collect();
+        assert_eq!(deprecated, vec![
+            ("allow", "allows", 4),
+            ("deny", "denies", 6),
+            ("override", "overrides", 7),
+        ]);
+        assert_eq!(
+            found.deprecated[0].to_string(),
+            "`allow` in `shell` is deprecated, use `allows`"
+        );
+    }
+}
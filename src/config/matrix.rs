@@ -0,0 +1,300 @@
+use {
+    ::kdl::{KdlDocument, KdlEntry, KdlNode, KdlValue},
+    serde::Serialize,
+    std::{collections::HashMap, fmt::Display},
+};
+
+const MATRIX: &str = "agent-matrix";
+/// Properties of `agent-matrix` passed on to every expanded agent
+const AGENT_PROPERTIES: [&str; 2] = ["template", "include-mcp-json"];
+
+/// The `agent-matrix` an agent was expanded from
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+pub struct MatrixOrigin {
+    /// Name of the matrix, e.g. `dev-{lang}`
+    pub pattern: String,
+    /// Variable values of this agent, in declaration order
+    pub values: Vec<(String, String)>,
+}
+
+impl Display for MatrixOrigin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.pattern)?;
+        for (k, v) in &self.values {
+            write!(f, " {k}={v}")?;
+        }
+        Ok(())
+    }
+}
+
+fn apply(values: &[(String, String)], s: &str) -> String {
+    let mut s = s.to_string();
+    for (k, v) in values {
+        s = s.replace(&format!("{{{k}}}"), v);
+    }
+    s
+}
+
+fn substitute_entry(entry: &mut KdlEntry, values: &[(String, String)]) {
+    let Some(s) = entry.value().as_string() else {
+        return;
+    };
+    let replaced = apply(values, s);
+    if replaced == s {
+        return;
+    }
+    entry.set_value(KdlValue::String(replaced));
+}
+
+fn substitute_node(node: &mut KdlNode, values: &[(String, String)]) {
+    node.entries_mut()
+        .iter_mut()
+        .for_each(|e| substitute_entry(e, values));
+    if let Some(children) = node.children_mut() {
+        children
+            .nodes_mut()
+            .iter_mut()
+            .for_each(|n| substitute_node(n, values));
+    }
+}
+
+/// A variable and its values, `lang="rust" "go"`
+type Variable = (String, Vec<String>);
+
+/// Variables of an `agent-matrix` node in declaration order. A property
+/// starts a variable, the positional arguments after it are more values:
+/// `lang="rust" "go"` is `lang` with `rust` and `go`
+fn variables(node: &KdlNode) -> crate::Result<(String, Vec<Variable>)> {
+    let mut entries = node.entries().iter();
+    let Some(pattern) = entries
+        .next()
+        .filter(|e| e.name().is_none())
+        .and_then(|e| e.value().as_string())
+    else {
+        return Err(crate::format_err!(
+            "{MATRIX} needs a name pattern, e.g. {MATRIX} \"dev-{{lang}}\" lang=\"rust\" \"go\""
+        ));
+    };
+    let mut variables: Vec<Variable> = Vec::new();
+    for entry in entries {
+        if entry
+            .name()
+            .is_some_and(|n| AGENT_PROPERTIES.contains(&n.value()))
+        {
+            continue;
+        }
+        let value = entry.value().as_string().map(String::from).ok_or_else(|| {
+            crate::format_err!("{MATRIX} '{pattern}' values must be strings, got {entry}")
+        })?;
+        match (entry.name(), variables.last_mut()) {
+            (Some(name), _) => variables.push((name.value().to_string(), vec![value])),
+            (None, Some((_, values))) => values.push(value),
+            (None, None) => {
+                return Err(crate::format_err!(
+                    "{MATRIX} '{pattern}' value \"{value}\" has no variable, use name=\"{value}\""
+                ));
+            }
+        }
+    }
+    if variables.is_empty() {
+        return Err(crate::format_err!("{MATRIX} '{pattern}' has no variables"));
+    }
+    for (name, values) in &variables {
+        if values.len() > 1 && !pattern.contains(&format!("{{{name}}}")) {
+            return Err(crate::format_err!(
+                "{MATRIX} '{pattern}' must use {{{name}}} in its name, it has {} values",
+                values.len()
+            ));
+        }
+    }
+    Ok((pattern.to_string(), variables))
+}
+
+/// Every combination of the variable values, in declaration order
+fn combinations(variables: &[Variable]) -> Vec<Vec<(String, String)>> {
+    variables
+        .iter()
+        .fold(vec![Vec::new()], |combos, (name, values)| {
+            combos
+                .iter()
+                .flat_map(|combo| {
+                    values.iter().map(move |v| {
+                        let mut c = combo.clone();
+                        c.push((name.clone(), v.clone()));
+                        c
+                    })
+                })
+                .collect()
+        })
+}
+
+/// `content` with every top level `agent-matrix` node written as an `agent`
+/// named after its pattern, without its variables. The other lines are left
+/// as they are, so the lint and the deserializer report the lines of the
+/// original file. Returns `None` when there is no matrix or the document does
+/// not parse
+pub fn unexpanded(content: &str) -> Option<String> {
+    if !content.contains(MATRIX) {
+        return None;
+    }
+    let mut doc = content.parse::<KdlDocument>().ok()?;
+    let mut found = false;
+    for node in doc.nodes_mut() {
+        if node.name().value() != MATRIX {
+            continue;
+        }
+        found = true;
+        node.set_name("agent");
+        let mut gap = String::new();
+        let mut pattern = true;
+        node.entries_mut().retain(|e| {
+            let keep = match e.name() {
+                None => std::mem::take(&mut pattern),
+                Some(n) => AGENT_PROPERTIES.contains(&n.value()),
+            };
+            // keep the line continuations of the variables
+            if let Some(format) = e.format().filter(|f| !keep && f.leading.contains('\n')) {
+                gap.push_str(&format.leading);
+            }
+            keep
+        });
+        let children = node.children().is_some();
+        if let Some(format) = node.format_mut() {
+            match children {
+                true => format.before_children.insert_str(0, &gap),
+                false => format.before_terminator.insert_str(0, &gap),
+            }
+        }
+    }
+    found.then(|| doc.to_string())
+}
+
+/// Expand every top level `agent-matrix` node into one `agent` node per
+/// combination of its variables, with `{variable}` replaced in the name and
+/// every string of the body. Returns `None` when there is nothing to expand
+/// or the document does not parse, the KDL deserializer reports syntax errors
+pub fn expand(content: &str) -> crate::Result<Option<(String, HashMap<String, MatrixOrigin>)>> {
+    if !content.contains(MATRIX) {
+        return Ok(None);
+    }
+    let Ok(mut doc) = content.parse::<KdlDocument>() else {
+        return Ok(None);
+    };
+    if !doc.nodes().iter().any(|n| n.name().value() == MATRIX) {
+        return Ok(None);
+    }
+    let mut origins = HashMap::new();
+    let mut nodes = Vec::with_capacity(doc.nodes().len());
+    for node in doc.nodes_mut().drain(..) {
+        if node.name().value() != MATRIX {
+            nodes.push(node);
+            continue;
+        }
+        let (pattern, variables) = variables(&node)?;
+        for values in combinations(&variables) {
+            let name = apply(&values, &pattern);
+            let mut agent = node.clone();
+            agent.set_name("agent");
+            agent.entries_mut().retain(|e| {
+                e.name()
+                    .is_some_and(|n| AGENT_PROPERTIES.contains(&n.value()))
+            });
+            agent.entries_mut().insert(0, KdlEntry::new(name.clone()));
+            substitute_node(&mut agent, &values);
+            // the original formatting would print the old values
+            agent.autoformat();
+            tracing::trace!("{MATRIX} '{pattern}' expanded to agent '{name}'");
+            if origins
+                .insert(name.clone(), MatrixOrigin {
+                    pattern: pattern.clone(),
+                    values,
+                })
+                .is_some()
+            {
+                return Err(crate::format_err!(
+                    "{MATRIX} '{pattern}' expands to agent '{name}' more than once"
+                ));
+            }
+            nodes.push(agent);
+        }
+    }
+    *doc.nodes_mut() = nodes;
+    Ok(Some((doc.to_string(), origins)))
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::config::{GeneratorConfig, GeneratorConfigDoc, kdl_parse},
+        miette::IntoDiagnostic,
+    };
+
+    #[test_log::test]
+    fn test_expand_matrix() -> crate::Result<()> {
+        let (content, origins) = expand(
+            r#"
+agent "rust-base" template=#true {}
+agent-matrix "dev-{lang}-{os}" lang="rust" "go" os="linux" template=#false {
+    inherits "{lang}-base"
+    description "{lang} on {os}"
+    allowed-tools "@{lang}docs" "read"
+}
+"#,
+        )?
+        .expect("expanded");
+        assert_eq!(origins.len(), 2);
+        assert_eq!(origins["dev-go-linux"], MatrixOrigin {
+            pattern: "dev-{lang}-{os}".into(),
+            values: vec![("lang".into(), "go".into()), ("os".into(), "linux".into())],
+        });
+
+        let doc: GeneratorConfigDoc = kdl_parse(&content)?;
+        let config = GeneratorConfig::from(doc);
+        assert_eq!(config.agents.len(), 3);
+        let rust = config.get("dev-rust-linux").expect("dev-rust-linux");
+        assert_eq!(rust.inherits, vec!["rust-base"]);
+        assert_eq!(rust.description.as_deref(), Some("rust on linux"));
+        assert!(rust.allowed_tools.contains("@rustdocs"));
+        assert!(rust.allowed_tools.contains("read"));
+        assert_eq!(rust.template, Some(false));
+        let go = config.get("dev-go-linux").expect("dev-go-linux");
+        assert_eq!(go.inherits, vec!["go-base"]);
+
+        assert!(expand(r#"agent "plain" {}"#)?.is_none());
+        assert!(expand(r#"agent-matrix "dev" lang="rust" "go" {}"#).is_err());
+        assert!(expand(r#"agent-matrix "dev-{lang}" "rust" {}"#).is_err());
+        Ok(())
+    }
+    #[tokio::test]
+    #[test_log::test]
+    async fn test_matrix_error_lines() -> crate::Result<()> {
+        let kdl = r#"agent "base" template=#true {}
+agent-matrix "dev-{lang}" lang="rust" \
+    "go" {
+    inherits "base"
+    toolz "read"
+}
+"#;
+        let original = unexpanded(kdl).expect("matrix");
+        assert_eq!(original.lines().count(), kdl.lines().count());
+        assert!(original.contains("agent \"dev-{lang}\""), "{original}");
+
+        let fs = crate::os::Fs::new();
+        let path = "matrix.kdl";
+        fs.write(path, kdl).await.into_diagnostic()?;
+        let Some(Err(err)) = GeneratorConfigDoc::from_path(&fs, path) else {
+            panic!("toolz is not a node of agent");
+        };
+        let found = crate::check::Finding::from_error(&err);
+        assert_eq!(found[0].line, Some(5));
+
+        fs.write(path, kdl.replace("toolz", "tools"))
+            .await
+            .into_diagnostic()?;
+        let (doc, origins) = GeneratorConfigDoc::from_path(&fs, path).expect("exists")?;
+        assert_eq!(doc.agents.len(), 3);
+        assert_eq!(origins.len(), 2);
+        Ok(())
+    }
+}
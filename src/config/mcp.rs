@@ -69,6 +69,7 @@ mod tests {
     use {
         super::*,
         crate::config::{ConfigResult, kdl_parse},
+        facet_kdl as kdl,
         indoc::indoc,
         std::collections::HashMap,
     };
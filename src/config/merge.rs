@@ -8,15 +8,42 @@ impl KdlAgent {
         self.description = self.description.or(other.description);
         self.prompt = self.prompt.or(other.prompt);
         self.model = self.model.or(other.model);
+        self.output = self.output.or(other.output);
 
-        // Collections are extended (merged)
-        self.resources.extend(other.resources);
-        self.tools.extend(other.tools);
-        self.allowed_tools.extend(other.allowed_tools);
+        // Collections are extended (merged), unless declared with
+        // merge="replace"
+        if !self.replace.contains("resources") {
+            self.resources.extend(other.resources);
+        }
+        if !self.replace.contains("tools") {
+            self.tools.extend(other.tools);
+        }
+        if !self.replace.contains("allowed-tools") {
+            self.allowed_tools.extend(other.allowed_tools);
+        }
         self.alias.extend(other.alias);
         self.mcp.extend(other.mcp);
-        self.inherits.extend(other.inherits);
+        // parents of the overriding side come last, so they win
+        let own = std::mem::replace(&mut self.inherits, other.inherits);
+        for parent in own {
+            if !self.inherits.contains(&parent) {
+                self.inherits.push(parent);
+            }
+        }
+        for (parent, args) in other.inherit_args {
+            let own = self.inherit_args.entry(parent).or_default();
+            for (k, v) in args {
+                own.entry(k).or_insert(v);
+            }
+        }
+        for (name, default) in other.params {
+            let own = self.params.entry(name).or_default();
+            if own.is_none() {
+                *own = default;
+            }
+        }
         self.tool_setting.extend(other.tool_setting);
+        self.when.extend(other.when);
 
         self.hook = self.hook.merge(other.hook);
         self.native_tool = self.native_tool.merge(other.native_tool);
@@ -25,6 +52,60 @@ impl KdlAgent {
     }
 }
 
+impl KdlAgent {
+    /// Forget `merge="replace"` once the agent is merged with its ancestors,
+    /// so merging it with a sibling parent keeps the sibling's values
+    pub fn replace_applied(mut self) -> Self {
+        self.replace.clear();
+        self.native_tool = self.native_tool.replace_applied();
+        self
+    }
+}
+
+/// Scalar fields where the child value wins
+fn scalars(agent: &KdlAgent) -> [(&'static str, Option<String>); 4] {
+    [
+        ("description", agent.description.clone()),
+        ("prompt", agent.prompt.clone()),
+        ("model", agent.model.clone()),
+        (
+            "include-mcp-json",
+            agent.include_mcp_json.map(|b| b.to_string()),
+        ),
+    ]
+}
+
+impl KdlAgent {
+    /// Scalars that more than one of the direct `parents`, each merged with
+    /// its own ancestors, sets to different values and this agent does not
+    /// set itself. The last parent wins, so
+    /// the result silently depends on the order of `inherits`
+    pub fn scalar_conflicts(&self, parents: &[KdlAgent]) -> Vec<String> {
+        let own = scalars(self);
+        let mut conflicts = Vec::new();
+        for (i, (field, value)) in own.iter().enumerate() {
+            if value.is_some() {
+                continue;
+            }
+            let set: Vec<(&str, String)> = parents
+                .iter()
+                .filter_map(|p| scalars(p)[i].1.clone().map(|v| (p.name.as_str(), v)))
+                .collect();
+            let Some((winner, winning)) = set.last() else {
+                continue;
+            };
+            if let Some((loser, _)) = set.iter().rev().find(|(_, v)| v != winning) {
+                conflicts.push(format!(
+                    "parents '{loser}' and '{winner}' of '{}' set different {field}, using \
+                     '{winner}'",
+                    self.name
+                ));
+            }
+        }
+        conflicts
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use {
@@ -129,7 +210,7 @@ mod tests {
         assert!(merged.include_mcp_json.unwrap_or_default());
 
         assert_eq!(merged.inherits.len(), 1);
-        assert!(merged.inherits.contains("parent"));
+        assert_eq!(merged.inherits, vec!["parent"]);
 
         assert_eq!(merged.prompt, Some("i tell you what to do".to_string()));
         let tools = &merged.tools;
@@ -183,4 +264,90 @@ mod tests {
         assert_eq!("child", format!("{merged:?}"));
         Ok(())
     }
+
+    #[test_log::test]
+    fn test_merge_replace() -> config::ConfigResult<()> {
+        let kdl_agents = indoc::indoc! {r#"
+            agent "child" {
+                allowed-tools merge="replace" "read"
+                tools "shell"
+                resource merge="replace" "file://child.md"
+                native-tool {
+                    shell merge="replace" {
+                        allows "cargo .*"
+                    }
+                    read {
+                        denies merge="replace" "secrets/**"
+                    }
+                }
+            }
+            agent "parent" {
+                allowed-tools "write" "shell"
+                tools "web_search"
+                resource "file://parent.md"
+                native-tool {
+                    shell deny-by-default=#true {
+                        allows "git .*"
+                    }
+                    read {
+                        allows "./src/*"
+                        denies "Cargo.lock"
+                    }
+                }
+            }
+        "#};
+        let config = GeneratorConfig::from(config::kdl_parse::<GeneratorConfigDoc>(kdl_agents)?);
+        let child = config.get("child").unwrap().clone();
+        let parent = config.get("parent").unwrap().clone();
+        let merged = child.merge(parent);
+
+        assert_eq!(merged.allowed_tools, HashSet::from(["read".to_string()]));
+        assert_eq!(
+            merged.resources,
+            HashSet::from(["file://child.md".to_string()])
+        );
+        // tools keeps the default union
+        assert!(merged.tools.contains("web_search"));
+        assert!(merged.tools.contains("shell"));
+
+        let shell = merged.get_tool_shell();
+        assert_eq!(shell.allows, HashSet::from(["cargo .*".to_string()]));
+        assert_eq!(shell.deny_by_default, None);
+        let read = merged.get_tool_read();
+        assert!(read.allows.contains("./src/*"));
+        assert_eq!(read.denies, HashSet::from(["secrets/**".to_string()]));
+        Ok(())
+    }
+
+    #[test_log::test]
+    fn test_scalar_conflicts() -> config::ConfigResult<()> {
+        let kdl_agents = indoc::indoc! {r#"
+            agent "a" { model "fast"; prompt "a"; }
+            agent "b" { model "slow"; description "b"; }
+            agent "c" { model "slow"; }
+            agent "child" { inherits "a" "b" "c"; prompt "mine"; }
+            agent "reversed" { inherits "c" "b" "a"; }
+        "#};
+        let config = GeneratorConfig::from(config::kdl_parse::<GeneratorConfigDoc>(kdl_agents)?);
+        let parents = |agent: &KdlAgent| -> Vec<KdlAgent> {
+            agent
+                .inherits
+                .iter()
+                .map(|p| config.get(p).unwrap().clone())
+                .collect()
+        };
+        let child = config.get("child").unwrap();
+        assert_eq!(child.inherits, vec!["a", "b", "c"]);
+        let conflicts = child.scalar_conflicts(&parents(child));
+        assert_eq!(conflicts, vec![
+            "parents 'a' and 'c' of 'child' set different model, using 'c'"
+        ]);
+
+        let reversed = config.get("reversed").unwrap();
+        let conflicts = reversed.scalar_conflicts(&parents(reversed));
+        assert_eq!(conflicts, vec![
+            "parents 'b' and 'a' of 'reversed' set different model, using 'a'"
+        ]);
+        Ok(())
+    }
 }
@@ -1,5 +1,5 @@
 use {
-    super::GenericSet,
+    super::{GenericSet, replaces},
     crate::agent::{
         AwsTool as KiroAwsTool,
         ExecuteShellTool as KiroShellTool,
@@ -20,11 +20,15 @@ macro_rules! define_tool {
             pub overrides: HashSet<String>,
             pub disable_auto_readonly: Option<bool>,
             pub deny_by_default: Option<bool>,
+            /// Lists declared with `merge="replace"`, parent values are dropped
+            pub replace: HashSet<String>,
         }
 
         impl $name {
             pub fn merge(mut self, other: Self) -> Self {
-                if !other.allows.is_empty() {
+                if self.replace.contains("allows") {
+                    tracing::trace!(tool = stringify!($name), "replacing allows");
+                } else if !other.allows.is_empty() {
                     tracing::trace!(
                         tool = stringify!($name),
                         count = other.allows.len(),
@@ -32,7 +36,9 @@ macro_rules! define_tool {
                     );
                     self.allows.extend(other.allows);
                 }
-                if !other.denies.is_empty() {
+                if self.replace.contains("denies") {
+                    tracing::trace!(tool = stringify!($name), "replacing denies");
+                } else if !other.denies.is_empty() {
                     tracing::trace!(
                         tool = stringify!($name),
                         count = other.denies.len(),
@@ -40,7 +46,9 @@ macro_rules! define_tool {
                     );
                     self.denies.extend(other.denies);
                 }
-                if !other.overrides.is_empty() {
+                if self.replace.contains("overrides") {
+                    tracing::trace!(tool = stringify!($name), "replacing overrides");
+                } else if !other.overrides.is_empty() {
                     tracing::trace!(
                         tool = stringify!($name),
                         count = other.overrides.len(),
@@ -62,16 +70,19 @@ macro_rules! define_kdl_doc {
         #[derive(Facet, Clone, Debug, Default, PartialEq, Eq)]
         #[facet(default, rename_all = "kebab-case")]
         pub struct $name {
-            #[facet(default, kdl::child)]
+            #[facet(default, kdl::child, alias = "allow")]
             pub(super) allows: GenericSet,
-            #[facet(default, kdl::child)]
+            #[facet(default, kdl::child, alias = "deny")]
             pub(super) denies: GenericSet,
-            #[facet(default, kdl::child)]
+            #[facet(default, kdl::child, alias = "override")]
             pub(super) overrides: GenericSet,
             #[facet(default, kdl::property)]
             pub deny_by_default: Option<bool>,
             #[facet(default, kdl::property)]
             pub disable_auto_readonly: Option<bool>,
+            /// `merge="replace"` drops the parent's settings for this tool
+            #[facet(default, kdl::property)]
+            pub merge: Option<String>,
         }
     };
 }
@@ -80,12 +91,22 @@ macro_rules! define_tool_into {
     ($name:ident, $to:ident) => {
         impl From<$name> for $to {
             fn from(value: $name) -> $to {
+                let replace = [
+                    ("allows", &value.allows),
+                    ("denies", &value.denies),
+                    ("overrides", &value.overrides),
+                ]
+                .into_iter()
+                .filter(|(field, list)| replaces(field, list.merge.as_deref()))
+                .map(|(field, _)| field.to_string())
+                .collect();
                 $to {
                     allows: value.allows.item,
                     denies: value.denies.item,
                     overrides: value.overrides.item,
                     deny_by_default: value.deny_by_default,
                     disable_auto_readonly: value.disable_auto_readonly,
+                    replace,
                 }
             }
         }
@@ -124,11 +145,24 @@ pub struct NativeTools {
     pub aws: AwsTool,
     pub read: ReadTool,
     pub write: WriteTool,
+    /// Tools declared with `merge="replace"`
+    pub replace: HashSet<String>,
 }
 
 impl From<NativeToolsDoc> for NativeTools {
     fn from(value: NativeToolsDoc) -> Self {
+        let replace = [
+            ("shell", value.shell.merge.as_deref()),
+            ("aws", value.aws.merge.as_deref()),
+            ("read", value.read.merge.as_deref()),
+            ("write", value.write.merge.as_deref()),
+        ]
+        .into_iter()
+        .filter(|(tool, merge)| replaces(tool, *merge))
+        .map(|(tool, _)| tool.to_string())
+        .collect();
         Self {
+            replace,
             shell: value.shell.into(),
             aws: value.aws.into(),
             read: value.read.into(),
@@ -139,10 +173,28 @@ impl From<NativeToolsDoc> for NativeTools {
 
 impl NativeTools {
     pub fn merge(mut self, other: Self) -> Self {
-        self.shell = self.shell.merge(other.shell);
-        self.aws = self.aws.merge(other.aws);
-        self.read = self.read.merge(other.read);
-        self.write = self.write.merge(other.write);
+        if !self.replace.contains("shell") {
+            self.shell = self.shell.merge(other.shell);
+        }
+        if !self.replace.contains("aws") {
+            self.aws = self.aws.merge(other.aws);
+        }
+        if !self.replace.contains("read") {
+            self.read = self.read.merge(other.read);
+        }
+        if !self.replace.contains("write") {
+            self.write = self.write.merge(other.write);
+        }
+        self
+    }
+
+    /// See `KdlAgent::replace_applied`
+    pub fn replace_applied(mut self) -> Self {
+        self.replace.clear();
+        self.shell.replace.clear();
+        self.aws.replace.clear();
+        self.read.replace.clear();
+        self.write.replace.clear();
         self
     }
 }
@@ -309,6 +361,49 @@ mod tests {
         Ok(())
     }
 
+    #[test_log::test]
+    fn parse_singular_and_plural() -> ConfigResult<()> {
+        let kdl = r#"
+            shell {
+                allow "ls .*"
+                allows "git status" "git fetch"
+                allow "pwd"
+                deny "rm .*"
+                denies "git push .*"
+                override "git commit .*"
+                overrides "git pull .*"
+            }
+            aws {
+                allow "s3"
+                allows "ec2"
+            }
+            read {
+                deny "/etc/*"
+                override "/etc/hosts"
+            }
+            write {
+                allow "*.txt"
+                allows "*.md"
+            }
+        "#;
+
+        let doc = NativeTools::from(kdl_parse::<NativeToolsDoc>(kdl)?);
+        assert_eq!(
+            doc.shell.allows,
+            into_set(vec!["ls .*", "git status", "git fetch", "pwd"])
+        );
+        assert_eq!(doc.shell.denies, into_set(vec!["rm .*", "git push .*"]));
+        assert_eq!(
+            doc.shell.overrides,
+            into_set(vec!["git commit .*", "git pull .*"])
+        );
+        assert_eq!(doc.aws.allows, into_set(vec!["s3", "ec2"]));
+        assert_eq!(doc.read.denies.len(), 1);
+        assert_eq!(doc.read.overrides.len(), 1);
+        assert_eq!(doc.write.allows, into_set(vec!["*.txt", "*.md"]));
+        Ok(())
+    }
+
     #[test_log::test]
     pub fn test_native_merge_empty() -> Result<()> {
         let child = NativeTools::default();
@@ -329,6 +424,7 @@ mod tests {
                 overrides: Default::default(),
                 allows: into_set(vec!["ec2"]),
                 denies: into_set(vec!["iam"]),
+                replace: Default::default(),
             },
             shell: ExecuteShellTool {
                 allows: into_set(vec!["ls .*"]),
@@ -336,6 +432,7 @@ mod tests {
                 overrides: into_set(vec!["rm -rf /"]),
                 deny_by_default: Some(true),
                 disable_auto_readonly: Some(false),
+                replace: Default::default(),
             },
             read: ReadTool {
                 allows: into_set(vec!["ls .*"]),
@@ -349,6 +446,7 @@ mod tests {
                 overrides: into_set(vec!["rm -rf /"]),
                 ..Default::default()
             },
+            replace: Default::default(),
         };
 
         let merged = child.merge(parent.clone());
@@ -467,6 +565,7 @@ mod tests {
                 deny_by_default: None,
                 disable_auto_readonly: None,
                 overrides: into_set(vec!["rm"]),
+                replace: Default::default(),
             },
             ..Default::default()
         };
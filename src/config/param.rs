@@ -0,0 +1,374 @@
+use {
+    super::{
+        GenericVec,
+        KdlAgent,
+        did_you_mean,
+        hook::{HookPart, NamedHooks},
+        native::{AwsTool, ExecuteShellTool, NativeTools, ReadTool, WriteTool},
+    },
+    crate::agent::{
+        CustomToolConfig,
+        hook::{Hook, HookScript},
+    },
+    ::kdl::{KdlDocument, KdlDocumentFormat, KdlNode, KdlValue},
+    facet::Facet,
+    facet_kdl as kdl,
+    std::{
+        borrow::Cow,
+        collections::{HashMap, HashSet},
+    },
+};
+
+const INHERITS: &str = "inherits";
+
+/// `param "service" default="s3"`, a parameter of a template. Without a
+/// default every child must pass it
+#[derive(Facet, Clone, Debug, Default)]
+#[facet(default)]
+pub(super) struct ParamDoc {
+    #[facet(kdl::argument)]
+    pub name: String,
+    #[facet(kdl::property, default)]
+    pub default: Option<String>,
+}
+
+/// `inherits "aws-base" "rust-base" service="ec2"`, the properties are passed
+/// as arguments to every parent on the node. [`inherits_properties`] moves
+/// them into `param` children before the node is deserialized
+#[derive(Facet, Clone, Debug, Default)]
+#[facet(default)]
+pub(super) struct InheritsDoc {
+    #[facet(kdl::arguments)]
+    pub item: Vec<String>,
+    #[facet(kdl::children, default)]
+    params: Vec<GenericVec>,
+}
+
+impl InheritsDoc {
+    /// Properties of the node, as `param "name" "value"` children
+    pub fn args(&self) -> Params {
+        self.params
+            .iter()
+            .cloned()
+            .flat_map(HashMap::<String, String>::from)
+            .collect()
+    }
+}
+
+/// `content` with the properties of every `inherits` node written as `param
+/// "name" "value"` children on the line of the node, so the deserializer
+/// reports the lines of the original file. `content` is left as it is when
+/// no `inherits` node has properties or the document does not parse
+pub(super) fn inherits_properties(content: &str) -> Cow<'_, str> {
+    if !content.contains(INHERITS) {
+        return Cow::Borrowed(content);
+    }
+    let Ok(mut doc) = content.parse::<KdlDocument>() else {
+        return Cow::Borrowed(content);
+    };
+    match move_properties(&mut doc) {
+        true => Cow::Owned(doc.to_string()),
+        false => Cow::Borrowed(content),
+    }
+}
+
+fn move_properties(doc: &mut KdlDocument) -> bool {
+    let mut moved = false;
+    for node in doc.nodes_mut() {
+        if let Some(children) = node.children_mut() {
+            moved |= move_properties(children);
+        }
+        if node.name().value() != INHERITS || node.entries().iter().all(|e| e.name().is_none()) {
+            continue;
+        }
+        moved = true;
+        let (properties, arguments) = node
+            .entries()
+            .iter()
+            .cloned()
+            .partition::<Vec<_>, _>(|e| e.name().is_some());
+        *node.entries_mut() = arguments;
+        let params = properties.into_iter().map(|p| {
+            let name = p.name().map(|n| n.value()).unwrap_or_default();
+            let value = match p.value() {
+                KdlValue::String(s) => s.clone(),
+                value => value.to_string(),
+            };
+            let mut param = KdlNode::new("param");
+            param.push(name);
+            param.push(value);
+            param.autoformat();
+            if let Some(format) = param.format_mut() {
+                format.leading = " ".into();
+                format.terminator = ";".into();
+                format.trailing = String::new();
+            }
+            param
+        });
+        match node.children_mut() {
+            Some(children) => {
+                let nodes = children.nodes_mut();
+                let rest = std::mem::take(nodes);
+                nodes.extend(params);
+                nodes.extend(rest);
+            }
+            None => {
+                let mut children = KdlDocument::new();
+                children.nodes_mut().extend(params);
+                children.set_format(KdlDocumentFormat {
+                    leading: String::new(),
+                    trailing: " ".into(),
+                });
+                node.set_children(children);
+                if let Some(format) = node.format_mut() {
+                    format.before_children = " ".into();
+                }
+            }
+        }
+    }
+    moved
+}
+
+/// Values bound to the parameters of an agent, substituted for `{name}`
+pub type Params = HashMap<String, String>;
+
+fn apply(params: &Params, value: &str) -> String {
+    let mut value = value.to_string();
+    for (name, v) in params {
+        value = value.replace(&format!("{{{name}}}"), v);
+    }
+    value
+}
+
+/// Replace `{param}` placeholders in every string field
+pub(super) trait Substitute {
+    fn substitute(&mut self, params: &Params);
+}
+
+impl Substitute for String {
+    fn substitute(&mut self, params: &Params) {
+        if self.contains('{') {
+            *self = apply(params, self);
+        }
+    }
+}
+
+impl<T: Substitute> Substitute for Option<T> {
+    fn substitute(&mut self, params: &Params) {
+        if let Some(v) = self {
+            v.substitute(params);
+        }
+    }
+}
+
+impl<T: Substitute> Substitute for Vec<T> {
+    fn substitute(&mut self, params: &Params) {
+        self.iter_mut().for_each(|v| v.substitute(params));
+    }
+}
+
+impl Substitute for HashSet<String> {
+    fn substitute(&mut self, params: &Params) {
+        *self = self.drain().map(|v| apply(params, &v)).collect();
+    }
+}
+
+impl<T: Substitute> Substitute for HashMap<String, T> {
+    fn substitute(&mut self, params: &Params) {
+        self.values_mut().for_each(|v| v.substitute(params));
+    }
+}
+
+impl Substitute for Hook {
+    fn substitute(&mut self, params: &Params) {
+        self.command.substitute(params);
+        self.matcher.substitute(params);
+        if let Some(HookScript::Inline(script)) = &mut self.script {
+            script.substitute(params);
+        }
+    }
+}
+
+impl Substitute for NamedHooks {
+    fn substitute(&mut self, params: &Params) {
+        self.values_mut().for_each(|v| v.substitute(params));
+    }
+}
+
+impl Substitute for HookPart {
+    fn substitute(&mut self, params: &Params) {
+        self.agent_spawn.substitute(params);
+        self.user_prompt_submit.substitute(params);
+        self.pre_tool_use.substitute(params);
+        self.post_tool_use.substitute(params);
+        self.stop.substitute(params);
+    }
+}
+
+impl Substitute for CustomToolConfig {
+    fn substitute(&mut self, params: &Params) {
+        self.url.substitute(params);
+        self.headers.substitute(params);
+        self.command.substitute(params);
+        self.args.substitute(params);
+        self.env.substitute(params);
+    }
+}
+
+macro_rules! substitute_tool {
+    ($name:ident) => {
+        impl Substitute for $name {
+            fn substitute(&mut self, params: &Params) {
+                self.allows.substitute(params);
+                self.denies.substitute(params);
+                self.overrides.substitute(params);
+            }
+        }
+    };
+}
+
+substitute_tool!(ExecuteShellTool);
+substitute_tool!(AwsTool);
+substitute_tool!(ReadTool);
+substitute_tool!(WriteTool);
+
+impl Substitute for NativeTools {
+    fn substitute(&mut self, params: &Params) {
+        self.shell.substitute(params);
+        self.aws.substitute(params);
+        self.read.substitute(params);
+        self.write.substitute(params);
+    }
+}
+
+impl KdlAgent {
+    /// Bind `args` passed by `child` to the declared parameters, using
+    /// defaults for the rest. Errors when a parameter without a default is
+    /// missing, unless `child` is `None` and the agent is a template
+    pub fn bind(&self, child: Option<&str>, args: &Params) -> crate::Result<Params> {
+        for name in args.keys().filter(|k| !self.params.contains_key(*k)) {
+            let help = did_you_mean(name, self.params.keys().map(String::as_str))
+                .map(|s| format!(", did you mean '{s}'?"))
+                .unwrap_or_default();
+            tracing::warn!(
+                target: crate::check::POLICY,
+                "'{}' passes unknown param '{name}' to '{}'{help}",
+                child.unwrap_or(&self.name),
+                self.name
+            );
+        }
+        let mut values = Params::with_capacity(self.params.len());
+        let mut missing = Vec::new();
+        for (name, default) in &self.params {
+            match args.get(name).or(default.as_ref()) {
+                Some(v) => {
+                    values.insert(name.clone(), v.clone());
+                }
+                None => missing.push(name.as_str()),
+            }
+        }
+        if missing.is_empty() || (child.is_none() && self.is_template()) {
+            return Ok(values);
+        }
+        missing.sort();
+        let example: Vec<String> = missing.iter().map(|m| format!("{m}=\"...\"")).collect();
+        Err(match child {
+            Some(child) => crate::format_err!(
+                help = format!("inherits \"{}\" {}", self.name, example.join(" ")),
+                "agent '{child}' inherits '{}' without required param {}",
+                self.name,
+                missing.join(", ")
+            ),
+            None => crate::format_err!(
+                help = format!("param \"{}\" default=\"...\"", missing[0]),
+                "agent '{}' has no value for param {}",
+                self.name,
+                missing.join(", ")
+            ),
+        })
+    }
+
+    /// Arguments passed to `parent`, with this agent's own bound `values`
+    /// substituted, e.g. `inherits "aws-base" service="{service}"`
+    pub fn args_for(&self, parent: &str, values: &Params) -> Params {
+        let mut args = self.inherit_args.get(parent).cloned().unwrap_or_default();
+        args.substitute(values);
+        args
+    }
+
+    /// Replace `{param}` in every string field with the bound values
+    pub fn substitute(mut self, params: &Params) -> Self {
+        if params.is_empty() {
+            return self;
+        }
+        self.description.substitute(params);
+        self.prompt.substitute(params);
+        self.resources.substitute(params);
+        self.tools.substitute(params);
+        self.allowed_tools.substitute(params);
+        self.model.substitute(params);
+        self.output.substitute(params);
+        self.hook.substitute(params);
+        self.mcp.substitute(params);
+        self.alias.substitute(params);
+        self.native_tool.substitute(params);
+        self.inherit_args.substitute(params);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::config::{GeneratorConfig, GeneratorConfigDoc, kdl_parse},
+    };
+
+    #[test_log::test]
+    fn test_params() -> crate::Result<()> {
+        let doc: GeneratorConfigDoc = kdl_parse(
+            r#"
+            agent "aws-base" template=#true {
+                param "service" default="s3"
+                param "region"
+                description "{service} in {region}"
+                allowed-tools "@aws-{service}"
+                native-tool {
+                    aws {
+                        allows "{service}"
+                    }
+                }
+            }
+            agent "ec2" {
+                inherits "aws-base" service="ec2" region="eu-west-1"
+                inherits "other"
+            }
+            "#,
+        )?;
+        let config = GeneratorConfig::from(doc);
+        let ec2 = config.get("ec2").unwrap();
+        assert_eq!(ec2.inherits, vec!["aws-base", "other"]);
+        let args = ec2.inherit_args.get("aws-base").unwrap();
+        assert_eq!(args.len(), 2);
+        assert!(!ec2.inherit_args.contains_key("other"));
+
+        let base = config.get("aws-base").unwrap();
+        let values = base.bind(Some("ec2"), args)?;
+        let resolved = base.clone().substitute(&values);
+        assert_eq!(resolved.description.as_deref(), Some("ec2 in eu-west-1"));
+        assert!(resolved.allowed_tools.contains("@aws-ec2"));
+        assert!(resolved.get_tool_aws().allows.contains("ec2"));
+
+        // defaults apply, required params are a diagnostic
+        let values = base.bind(Some("s3"), &Params::from([("region".into(), "x".into())]))?;
+        assert_eq!(values.get("service").map(String::as_str), Some("s3"));
+        let err = base.bind(Some("s3"), &Params::new()).unwrap_err();
+        assert!(
+            err.to_string().contains("without required param region"),
+            "{err}"
+        );
+        // a template on its own keeps its placeholders
+        assert!(base.bind(None, &Params::new()).is_ok());
+        Ok(())
+    }
+}
@@ -0,0 +1,130 @@
+use {
+    super::{KdlAgent, KdlAgentDoc, condition::Facts},
+    facet::Facet,
+    facet_kdl as kdl,
+    glob::Pattern,
+};
+
+/// `profile "ci" { agent "*" { ... } }`, patches applied after inheritance
+/// when the profile is selected with `--profile`
+#[derive(Facet, Clone, Default)]
+#[facet(default)]
+pub struct ProfileDoc {
+    #[facet(kdl::argument)]
+    pub name: String,
+    /// agent names are globs selecting the agents to patch
+    #[facet(kdl::children, default)]
+    pub agents: Vec<KdlAgentDoc>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Profile {
+    pub name: String,
+    /// applied in order, later patches win
+    pub patches: Vec<KdlAgent>,
+}
+
+impl From<ProfileDoc> for Profile {
+    fn from(value: ProfileDoc) -> Self {
+        Self {
+            name: value.name,
+            patches: value.agents.into_iter().map(KdlAgent::from).collect(),
+        }
+    }
+}
+
+impl Profile {
+    pub fn evaluate(mut self, facts: &Facts) -> Self {
+        self.patches = self
+            .patches
+            .into_iter()
+            .map(|p| p.evaluate(facts))
+            .collect();
+        self
+    }
+
+    /// Append the patches of another block with the same name
+    pub fn extend(&mut self, other: Profile) {
+        if self.name.is_empty() {
+            self.name = other.name;
+        }
+        self.patches.extend(other.patches);
+    }
+
+    /// Apply every patch whose name matches the agent
+    pub fn apply(&self, mut agent: KdlAgent) -> KdlAgent {
+        for patch in &self.patches {
+            let matches = match Pattern::new(&patch.name) {
+                Ok(p) => p.matches(&agent.name),
+                Err(_) => patch.name == agent.name,
+            };
+            if matches {
+                tracing::trace!(
+                    profile = self.name,
+                    patch = patch.name,
+                    "patching {}",
+                    agent.name
+                );
+                agent = agent.patch(patch.clone());
+            }
+        }
+        agent
+    }
+}
+
+impl KdlAgent {
+    /// Merge `patch` into the agent, values from the patch win. The agent
+    /// keeps its name, template flag and parents
+    pub fn patch(mut self, patch: KdlAgent) -> Self {
+        let name = std::mem::take(&mut self.name);
+        let template = self.template;
+        let inherits = std::mem::take(&mut self.inherits);
+        let mut patched = patch.merge(self);
+        patched.name = name;
+        patched.template = template;
+        patched.inherits = inherits;
+        patched
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::{GeneratorConfig, GeneratorConfigDoc, kdl_parse};
+
+    #[test_log::test]
+    fn test_profile_apply() -> crate::Result<()> {
+        let doc: GeneratorConfigDoc = kdl_parse(
+            r#"
+            agent "rust" { model "fast"; }
+            agent "aws-test" { model "fast"; }
+            profile "ci" {
+                agent "*" {
+                    native-tool {
+                        shell deny-by-default=#true
+                    }
+                }
+                agent "aws-*" {
+                    model "slow"
+                    allowed-tools "@awsdocs"
+                }
+            }
+            "#,
+        )?;
+        let config = GeneratorConfig::from(doc);
+        let ci = config.profiles.get("ci").expect("ci profile");
+        assert_eq!(ci.patches.len(), 2);
+
+        let rust = ci.apply(config.get("rust").unwrap().clone());
+        assert_eq!(rust.name, "rust");
+        assert_eq!(rust.model.as_deref(), Some("fast"));
+        assert_eq!(rust.get_tool_shell().deny_by_default, Some(true));
+        assert!(rust.allowed_tools.is_empty());
+
+        let aws = ci.apply(config.get("aws-test").unwrap().clone());
+        assert_eq!(aws.name, "aws-test");
+        assert_eq!(aws.model.as_deref(), Some("slow"));
+        assert!(aws.allowed_tools.contains("@awsdocs"));
+        assert_eq!(aws.get_tool_shell().deny_by_default, Some(true));
+        Ok(())
+    }
+}
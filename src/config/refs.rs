@@ -0,0 +1,161 @@
+use {
+    super::{KdlAgent, did_you_mean},
+    crate::agent::BUILTIN,
+    std::fmt::Display,
+};
+
+/// A `@server` or `@server/tool` reference to an MCP server that is missing
+/// or disabled in the merged agent
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DanglingRef {
+    /// `tools`, `allowed-tools` or `alias`
+    pub field: &'static str,
+    /// The reference as written, e.g. `@github/list_issues`
+    pub reference: String,
+    pub server: String,
+    /// The server exists but is disabled
+    pub disabled: bool,
+    /// Closest configured server name when it is missing
+    pub suggestion: Option<String>,
+}
+
+impl Display for DanglingRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} '{}' ", self.field, self.reference)?;
+        if self.disabled {
+            return write!(f, "refers to disabled mcp server '{}'", self.server);
+        }
+        write!(f, "refers to unknown mcp server '{}'", self.server)?;
+        if let Some(s) = &self.suggestion {
+            write!(f, ", did you mean '{s}'?")?;
+        }
+        Ok(())
+    }
+}
+
+/// Server name of `@server` or `@server/tool`, `@builtin` is not a server
+fn server_of(reference: &str) -> Option<&str> {
+    if reference == BUILTIN {
+        return None;
+    }
+    let name = reference.strip_prefix('@')?;
+    let server = name.split_once('/').map_or(name, |(s, _)| s);
+    (!server.is_empty()).then_some(server)
+}
+
+impl KdlAgent {
+    /// Every `@server` reference in tools, allowed-tools and alias whose
+    /// server is disabled or not configured. Missing servers are not
+    /// reported when the agent includes the workspace mcp.json, they may be
+    /// defined there
+    pub fn dangling_refs(&self) -> Vec<DanglingRef> {
+        let references = self
+            .tools
+            .iter()
+            .map(|t| ("tools", t))
+            .chain(self.allowed_tools.iter().map(|t| ("allowed-tools", t)))
+            .chain(self.alias.keys().map(|t| ("alias", t)));
+        let include_mcp_json = self.include_mcp_json.unwrap_or_default();
+        let mut dangling: Vec<DanglingRef> = references
+            .filter_map(|(field, reference)| {
+                let server = server_of(reference)?;
+                let disabled = match self.mcp.get(server) {
+                    Some(mcp) if mcp.disabled => true,
+                    Some(_) => return None,
+                    None if include_mcp_json => return None,
+                    None => false,
+                };
+                let suggestion = (!disabled)
+                    .then(|| did_you_mean(server, self.mcp.keys().map(String::as_str)))
+                    .flatten()
+                    .map(String::from);
+                Some(DanglingRef {
+                    field,
+                    reference: reference.clone(),
+                    server: server.to_string(),
+                    disabled,
+                    suggestion,
+                })
+            })
+            .collect();
+        dangling.sort_by(|a, b| (a.field, &a.reference).cmp(&(b.field, &b.reference)));
+        dangling
+    }
+
+    /// Remove references to disabled servers from tools, allowed-tools and
+    /// alias, so the generated agent only names servers Kiro will start
+    pub fn drop_disabled_refs(mut self) -> Self {
+        let disabled = |reference: &String| {
+            server_of(reference)
+                .and_then(|s| self.mcp.get(s))
+                .is_some_and(|mcp| mcp.disabled)
+        };
+        let tools = self
+            .tools
+            .iter()
+            .filter(|t| !disabled(t))
+            .cloned()
+            .collect();
+        let allowed_tools = self
+            .allowed_tools
+            .iter()
+            .filter(|t| !disabled(t))
+            .cloned()
+            .collect();
+        let alias = self
+            .alias
+            .iter()
+            .filter(|(k, _)| !disabled(k))
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        self.tools = tools;
+        self.allowed_tools = allowed_tools;
+        self.alias = alias;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::{GeneratorConfig, GeneratorConfigDoc, kdl_parse};
+
+    #[test_log::test]
+    fn test_dangling_refs() -> crate::Result<()> {
+        let doc: GeneratorConfigDoc = kdl_parse(
+            r#"
+            agent "dev" {
+                tools "*" "@builtin" "@github" "@gitlab/list_issues"
+                allowed-tools "read" "@github/get_issue" "@fetch"
+                alias "@fetch/fetch" "get"
+                mcp "github" {
+                    command "github-mcp-server"
+                }
+                mcp "fetch" disabled=#true {
+                    command "uvx"
+                }
+            }
+            "#,
+        )?;
+        let config = GeneratorConfig::from(doc);
+        let dev = config.get("dev").unwrap().clone();
+        let dangling = dev.dangling_refs();
+        assert_eq!(dangling.len(), 3, "{dangling:?}");
+        assert_eq!(
+            dangling[0].to_string(),
+            "alias '@fetch/fetch' refers to disabled mcp server 'fetch'"
+        );
+        assert_eq!(dangling[1].reference, "@fetch");
+        assert!(dangling[1].disabled);
+        assert_eq!(dangling[2].server, "gitlab");
+        assert_eq!(dangling[2].suggestion.as_deref(), Some("github"));
+
+        let dropped = dev.drop_disabled_refs();
+        assert!(!dropped.allowed_tools.contains("@fetch"));
+        assert!(dropped.allowed_tools.contains("@github/get_issue"));
+        assert!(dropped.alias.is_empty());
+        // missing servers are reported, not dropped
+        assert!(dropped.tools.contains("@gitlab/list_issues"));
+        assert_eq!(dropped.dangling_refs().len(), 1);
+        Ok(())
+    }
+}
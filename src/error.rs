@@ -1,5 +1,39 @@
-#[derive(Debug, thiserror::Error)]
+use std::path::PathBuf;
+
+#[derive(Debug, thiserror::Error, miette::Diagnostic)]
 pub enum Error {
     #[error("syntax error in file {0}")]
     DeserializeError(String, facet_kdl::KdlDeserializeError),
+    #[error("{0}")]
+    UnknownFields(crate::config::UnknownFields),
+    /// An agent kg cannot generate. `file` and `line` point at its
+    /// definition once the generator located it
+    #[error("agent '{agent}': {message}")]
+    InvalidAgent {
+        agent: String,
+        message: String,
+        #[help]
+        help: Option<String>,
+        file: Option<PathBuf>,
+        line: Option<usize>,
+    },
+}
+
+impl Error {
+    pub fn invalid_agent(agent: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::InvalidAgent {
+            agent: agent.into(),
+            message: message.into(),
+            help: None,
+            file: None,
+            line: None,
+        }
+    }
+
+    pub fn with_help(mut self, text: impl Into<String>) -> Self {
+        if let Self::InvalidAgent { help, .. } = &mut self {
+            *help = Some(text.into());
+        }
+        self
+    }
 }
@@ -0,0 +1,105 @@
+use {super::*, std::collections::BTreeSet};
+
+/// Agent files kg wrote, next to the scripts manifest in the hooks directory,
+/// so the files of removed agents can be removed. It is not kept with the
+/// agents, Kiro would read it as one
+const MANIFEST: &str = "kg-agents.json";
+
+type Manifest = BTreeSet<PathBuf>;
+
+impl Generator {
+    fn read_agent_manifest(&self, dir: &Path) -> Manifest {
+        let path = dir.join(MANIFEST);
+        if !self.fs.exists(&path) {
+            return Manifest::new();
+        }
+        self.fs
+            .read_to_string_sync(&path)
+            .ok()
+            .and_then(|m| serde_json::from_str(&m).ok())
+            .unwrap_or_else(|| {
+                tracing::warn!("ignoring unreadable {}", path.display());
+                Manifest::new()
+            })
+    }
+
+    async fn write_agent_manifest(&self, dir: &Path, manifest: &Manifest) -> Result<()> {
+        let path = dir.join(MANIFEST);
+        if manifest.is_empty() {
+            if self.fs.exists(&path) {
+                self.fs.remove_file(&path).await.into_diagnostic()?;
+            }
+            return Ok(());
+        }
+        if !self.fs.exists(dir) {
+            self.fs
+                .create_dir_all(dir)
+                .await
+                .into_diagnostic()
+                .wrap_err_with(|| format!("failed to create directory {}", dir.display()))?;
+        }
+        self.fs
+            .write(
+                &path,
+                serde_json::to_string_pretty(manifest).into_diagnostic()?,
+            )
+            .await
+            .into_diagnostic()
+            .wrap_err_with(|| format!("failed to write file {}", path.display()))
+    }
+
+    /// The agent files of `results` per hooks directory, every directory of
+    /// `results` is included
+    fn agent_files(&self, results: &[AgentResult]) -> BTreeMap<PathBuf, Manifest> {
+        let mut files: BTreeMap<PathBuf, Manifest> = BTreeMap::new();
+        for result in results {
+            let generated = files.entry(self.hooks_dir(&result.agent.name)).or_default();
+            if result.writable {
+                generated.insert(result.destination.clone());
+            }
+        }
+        files
+    }
+
+    /// Agent files kg wrote before that `results` no longer has and that
+    /// are still there
+    pub(crate) fn stale_agent_files(&self, results: &[AgentResult]) -> Vec<PathBuf> {
+        let mut stale = Vec::new();
+        for (dir, generated) in self.agent_files(results) {
+            stale.extend(
+                self.read_agent_manifest(&dir)
+                    .difference(&generated)
+                    .filter(|p| self.fs.exists(p))
+                    .cloned(),
+            );
+        }
+        stale
+    }
+
+    /// Add the agent files of `results` to the manifest. With `prune`, the
+    /// files of agents that were not generated are removed
+    pub(crate) async fn record_agent_files(
+        &self,
+        results: &[AgentResult],
+        prune: bool,
+    ) -> Result<()> {
+        for (dir, generated) in self.agent_files(results) {
+            let mut manifest = self.read_agent_manifest(&dir);
+            if prune {
+                for path in manifest.difference(&generated) {
+                    if self.fs.exists(path) {
+                        tracing::info!("removing agent file {}", path.display());
+                        if let Err(e) = self.fs.remove_file(path).await {
+                            tracing::warn!("failed to remove {}: {e}", path.display());
+                        }
+                    }
+                }
+                manifest = generated;
+            } else {
+                manifest.extend(generated);
+            }
+            self.write_agent_manifest(&dir, &manifest).await?;
+        }
+        Ok(())
+    }
+}
@@ -1,4 +1,76 @@
-use {super::*, std::fmt::Display};
+use {
+    super::*,
+    std::{fmt::Display, path::Path},
+};
+
+/// Find every ancestor of `start` (inclusive) that contains
+/// `.kiro/generators/kg.kdl`, nearest first. The search stops at the git root
+/// (a directory containing `.git`) or the filesystem root. `home` is never a
+/// project root, its `.kiro/generators` is the global config, so the search
+/// stops below it
+pub fn project_roots(fs: &Fs, start: impl AsRef<Path>, home: Option<&Path>) -> Vec<PathBuf> {
+    let mut roots = Vec::new();
+    for dir in start.as_ref().ancestors() {
+        if home.is_some_and(|h| h == dir) {
+            tracing::debug!("stopped looking for .kiro/generators at $HOME");
+            break;
+        }
+        if fs.exists(dir.join(".kiro").join("generators").join("kg.kdl")) {
+            roots.push(dir.to_path_buf());
+        }
+        if fs.exists(dir.join(".git")) {
+            tracing::debug!(
+                "stopped looking for .kiro/generators at git root {}",
+                dir.display()
+            );
+            break;
+        }
+    }
+    roots
+}
+
+/// A directory with a kg.kdl and per-agent `<agent-name>.kdl` files
+#[derive(Clone, Debug, Serialize)]
+pub struct ConfigLayer {
+    pub name: String,
+    pub dir: PathBuf,
+    /// Agents from local layers are written to the project, not $HOME
+    pub local: bool,
+}
+
+impl ConfigLayer {
+    pub fn new(name: impl Into<String>, dir: impl Into<PathBuf>, local: bool) -> Self {
+        Self {
+            name: name.into(),
+            dir: dir.into(),
+            local,
+        }
+    }
+
+    /// ~/.kiro/generators
+    pub fn global(dir: impl Into<PathBuf>) -> Self {
+        Self::new("global", dir, false)
+    }
+
+    /// ./.kiro/generators
+    pub fn local() -> Self {
+        Self::new("local", PathBuf::from(".kiro").join("generators"), true)
+    }
+
+    pub fn agent(&self, name: impl AsRef<str>) -> PathBuf {
+        self.dir.join(format!("{}.kdl", name.as_ref()))
+    }
+
+    pub fn kg(&self) -> PathBuf {
+        self.agent("kg")
+    }
+}
+
+impl Display for ConfigLayer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}={}", self.name, self.dir.display())
+    }
+}
 
 /// Represents where configuration files are located
 pub enum ConfigLocation {
@@ -8,77 +80,209 @@ pub enum ConfigLocation {
     Local,
     /// Both global and local configs (local overrides global)
     Both(PathBuf),
+    /// Any number of layers, lowest to highest priority
+    Layered(Vec<ConfigLayer>),
 }
 
 impl ConfigLocation {
-    pub fn global(&self, name: impl AsRef<str>) -> PathBuf {
-        let n = format!("{}.kdl", name.as_ref());
+    /// Config layers ordered from lowest to highest priority
+    pub fn layers(&self) -> Vec<ConfigLayer> {
         match self {
-            ConfigLocation::Global(path) | ConfigLocation::Both(path) => path.join(n),
-            #[cfg(not(test))]
-            ConfigLocation::Local => PathBuf::default(),
-            #[cfg(test)]
-            ConfigLocation::Local => PathBuf::from("dev").join("null").join(n), /* FS is chroot
-                                                                                 * in test */
+            Self::Global(path) => vec![ConfigLayer::global(path)],
+            Self::Local => vec![ConfigLayer::local()],
+            Self::Both(path) => vec![ConfigLayer::global(path), ConfigLayer::local()],
+            Self::Layered(layers) => layers.clone(),
         }
     }
 
-    pub fn local(&self, name: impl AsRef<str>) -> PathBuf {
-        match self {
-            Self::Local | Self::Both(_) => PathBuf::from(".kiro")
-                .join("generators")
-                .join(format!("{}.kdl", name.as_ref())),
-            #[cfg(not(test))]
-            Self::Global(_) => PathBuf::default(),
-            #[cfg(test)]
-            Self::Global(_) => PathBuf::from("dev").join("null"), // FS is chroot in test
+    /// Add lower priority layers, such as organization wide defaults
+    pub fn with_base_layers(self, base: Vec<ConfigLayer>) -> Self {
+        if base.is_empty() {
+            return self;
         }
+        let mut layers = base;
+        layers.extend(self.layers());
+        Self::Layered(layers)
     }
 
-    pub fn global_kg(&self) -> PathBuf {
-        self.global("kg")
+    /// Add layers for enclosing projects (e.g. the root of a monorepo) below
+    /// the local layer. `roots` are ordered nearest first, see
+    /// [`project_roots`]
+    pub fn with_project_layers(self, roots: &[PathBuf]) -> Self {
+        let mut layers = self.layers();
+        let Some(local) = layers.iter().position(|l| l.local) else {
+            return self;
+        };
+        if roots.is_empty() {
+            return self;
+        }
+        for root in roots {
+            layers.insert(
+                local,
+                ConfigLayer::new(
+                    format!("project:{}", root.display()),
+                    root.join(".kiro").join("generators"),
+                    true,
+                ),
+            );
+        }
+        Self::Layered(layers)
     }
 
-    pub fn local_kg(&self) -> PathBuf {
-        self.local("kg")
+    /// Drop local layers that are a global one seen from `cwd`, e.g. when
+    /// kg runs in $HOME, so its agents stay global
+    pub fn without_global_duplicates(self, cwd: &Path) -> Self {
+        let layers = self.layers();
+        let global: Vec<PathBuf> = layers
+            .iter()
+            .filter(|l| !l.local)
+            .map(|l| cwd.join(&l.dir))
+            .collect();
+        let (duplicates, kept): (Vec<ConfigLayer>, Vec<ConfigLayer>) = layers
+            .into_iter()
+            .partition(|l| l.local && global.contains(&cwd.join(&l.dir)));
+        if duplicates.is_empty() {
+            return self;
+        }
+        for layer in duplicates {
+            tracing::debug!("skipping layer {layer}, it is a global layer");
+        }
+        Self::Layered(kept)
     }
 
     /// Validates that at least one config file exists
     pub fn is_valid(&self, fs: &Fs) -> Result<()> {
-        let global_exists = fs.exists(self.global_kg());
-        let local_exists = fs.exists(self.local_kg());
-
-        if !global_exists && !local_exists {
-            return Err(crate::format_err!(
-                "no kg.kdl found at global ({}) or local ({})",
-                self.global_kg().display(),
-                self.local_kg().display()
-            ));
+        let layers = self.layers();
+        if layers.iter().any(|l| fs.exists(l.kg())) {
+            return Ok(());
         }
-        Ok(())
+        Err(crate::format_err!(
+            "no kg.kdl found in {}",
+            layers
+                .iter()
+                .map(|l| format!("{} ({})", l.name, l.kg().display()))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))
     }
 }
 
 impl Debug for ConfigLocation {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            ConfigLocation::Global(_) => write!(f, "[global]"),
-            ConfigLocation::Local => write!(f, "[local]"),
-            ConfigLocation::Both(_) => {
-                write!(f, "[global,local]")
-            }
-        }
+        let names: Vec<String> = self.layers().into_iter().map(|l| l.name).collect();
+        write!(f, "[{}]", names.join(","))
     }
 }
 
 impl Display for ConfigLocation {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            ConfigLocation::Global(p) => write!(f, "global={}", p.display()),
-            ConfigLocation::Local => write!(f, "local"),
-            ConfigLocation::Both(p) => {
-                write!(f, "global={},local", p.display())
-            }
+        let layers: Vec<String> = self.layers().iter().map(|l| l.to_string()).collect();
+        write!(f, "{}", layers.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn test_project_root() -> Result<()> {
+        let fs = Fs::new();
+        let repo = PathBuf::from("/work/repo");
+        let nested = repo.join("pkg").join("src");
+        fs.create_dir_all(&nested).await.into_diagnostic()?;
+        fs.create_dir_all(repo.join(".git"))
+            .await
+            .into_diagnostic()?;
+        assert!(project_roots(&fs, &nested, None).is_empty());
+
+        for dir in [&repo, &repo.join("pkg")] {
+            fs.create_dir_all(dir.join(".kiro").join("generators"))
+                .await
+                .into_diagnostic()?;
+            fs.write(dir.join(".kiro").join("generators").join("kg.kdl"), "")
+                .await
+                .into_diagnostic()?;
         }
+        assert_eq!(project_roots(&fs, &nested, None), vec![
+            repo.join("pkg"),
+            repo.clone()
+        ]);
+        assert_eq!(project_roots(&fs, &repo, None), vec![repo.clone()]);
+
+        // config above the git root is not used
+        fs.create_dir_all(repo.join("pkg").join(".git"))
+            .await
+            .into_diagnostic()?;
+        assert_eq!(project_roots(&fs, &nested, None), vec![repo.join("pkg")]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn test_project_root_stops_at_home() -> Result<()> {
+        let fs = Fs::new();
+        let home = PathBuf::from("/home/user");
+        let scratch = home.join("scratch");
+        fs.create_dir_all(&scratch).await.into_diagnostic()?;
+        fs.create_dir_all(home.join(".kiro").join("generators"))
+            .await
+            .into_diagnostic()?;
+        fs.write(home.join(".kiro").join("generators").join("kg.kdl"), "")
+            .await
+            .into_diagnostic()?;
+        assert_eq!(project_roots(&fs, &scratch, None)[0], home);
+        assert!(project_roots(&fs, &scratch, Some(&home)).is_empty());
+        assert!(project_roots(&fs, &home, Some(&home)).is_empty());
+
+        let global = home.join(".kiro").join("generators");
+        let names = |location: ConfigLocation| -> Vec<String> {
+            location.layers().into_iter().map(|l| l.name).collect()
+        };
+        let location = ConfigLocation::Both(global.clone()).without_global_duplicates(&home);
+        assert_eq!(names(location), vec!["global"]);
+        let location = ConfigLocation::Both(global).without_global_duplicates(&scratch);
+        assert_eq!(names(location), vec!["global", "local"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_layers() {
+        let home = PathBuf::from("/home/user/.kiro/generators");
+        let layers = ConfigLocation::Both(home.clone()).layers();
+        assert_eq!(layers.len(), 2);
+        assert!(!layers[0].local);
+        assert!(layers[1].local);
+
+        let location = ConfigLocation::Local.with_base_layers(vec![ConfigLayer::new(
+            "system",
+            "/etc/kiro/generators",
+            false,
+        )]);
+        let names: Vec<String> = location.layers().into_iter().map(|l| l.name).collect();
+        assert_eq!(names, vec!["system", "local"]);
+        assert_eq!(
+            location.layers()[0].agent("rust"),
+            PathBuf::from("/etc/kiro/generators/rust.kdl")
+        );
+        assert_eq!(format!("{location:?}"), "[system,local]");
+
+        let location = ConfigLocation::Both(home)
+            .with_project_layers(&[PathBuf::from("/repo/packages"), PathBuf::from("/repo")]);
+        let names: Vec<String> = location.layers().into_iter().map(|l| l.name).collect();
+        assert_eq!(names, vec![
+            "global",
+            "project:/repo",
+            "project:/repo/packages",
+            "local"
+        ]);
+        assert!(
+            ConfigLocation::Global(PathBuf::from("/g"))
+                .with_project_layers(&[PathBuf::from("/repo")])
+                .layers()
+                .len()
+                == 1
+        );
     }
 }
@@ -1,42 +1,134 @@
 use {
     super::*,
-    crate::config::{GeneratorConfig, GeneratorConfigDoc, KdlAgent, KdlAgentDoc},
-    std::{fmt::Display, ops::Deref, path::Path},
+    crate::config::{Facts, GeneratorConfig, GeneratorConfigDoc, KdlAgent, KdlAgentDoc, Profile},
+    config_location::ConfigLayer,
+    miette::{Context, IntoDiagnostic},
+    std::{
+        fmt::Display,
+        ops::Deref,
+        path::{Component, Path, PathBuf},
+    },
 };
 
 pub fn load_inline(fs: &Fs, path: impl AsRef<Path>) -> Result<GeneratorConfig> {
-    let doc: Option<Result<GeneratorConfigDoc>> = crate::config::kdl_parse_path(fs, path);
-    match doc {
-        None => Ok(GeneratorConfig::default()),
-        Some(d) => {
-            let d = d?;
-            Ok(d.into())
+    let mut config = GeneratorConfig::default();
+    if fs.exists(&path) {
+        load_config(
+            fs,
+            path.as_ref(),
+            &mut Vec::new(),
+            &mut HashSet::new(),
+            &mut config,
+        )?;
+    }
+    Ok(config)
+}
+
+/// Lexically resolve `.` and `..` so the same file reached through different
+/// includes compares equal
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir if normalized.file_name().is_some() => {
+                normalized.pop();
+            }
+            c => normalized.push(c),
         }
     }
+    normalized
 }
 
-fn process_local(
+/// Load a kg.kdl and, depth first, every file it includes into `config`.
+/// `stack` holds the files being loaded, `visited` every file loaded so far,
+/// both by canonical path. A file included twice, e.g. by two included files,
+/// is loaded once
+fn load_config(
     fs: &Fs,
-    name: impl AsRef<str>,
-    location: &ConfigLocation,
-    inline: Option<&KdlAgent>,
-    sources: &mut Vec<KdlAgentSource>,
-) -> Result<KdlAgent> {
-    let local_agent_path = location.local(&name);
-    let result = KdlAgentDoc::from_path(fs, &name, &local_agent_path);
-    match result {
-        None => Ok(KdlAgent::new(name.as_ref().to_string())),
-        Some(a) => {
-            let agent = KdlAgent::from(a?.clone());
-            sources.push(KdlAgentSource::LocalFile(local_agent_path));
-            if let Some(i) = inline {
-                sources.push(KdlAgentSource::LocalInline);
-                Ok(agent.merge(i.clone()))
-            } else {
-                Ok(agent)
+    path: &Path,
+    stack: &mut Vec<(PathBuf, PathBuf)>,
+    visited: &mut HashSet<PathBuf>,
+    config: &mut GeneratorConfig,
+) -> Result<()> {
+    let path = normalize(path);
+    let canonical = fs.canonicalize(&path).unwrap_or_else(|_| path.clone());
+    if let Some(i) = stack.iter().position(|(c, _)| *c == canonical) {
+        let chain: Vec<String> = stack[i..]
+            .iter()
+            .map(|(_, p)| p)
+            .chain([&path])
+            .map(|p| p.display().to_string())
+            .collect();
+        return Err(crate::format_err!(
+            "include cycle detected: {}",
+            chain.join(" -> ")
+        ));
+    }
+    if !visited.insert(canonical.clone()) {
+        tracing::debug!("{} is already included", path.display());
+        return Ok(());
+    }
+    let (doc, matrix) = match GeneratorConfigDoc::from_path(fs, &path) {
+        None => {
+            return Err(crate::format_err!(
+                "included file {} not found",
+                path.display()
+            ));
+        }
+        Some(d) => d?,
+    };
+    let includes = doc.includes.clone();
+    let fragment = GeneratorConfig::from(doc);
+    config.matrix.extend(matrix);
+    for (name, agent) in fragment.agents {
+        if let Some(existing) = config.files.get(&name) {
+            return Err(crate::format_err!(
+                "agent '{name}' is defined in both {} and {}",
+                existing.display(),
+                path.display()
+            ));
+        }
+        config.files.insert(name.clone(), path.clone());
+        config.agents.insert(name, agent);
+    }
+    if config.output_dir.is_none() {
+        config.output_dir = fragment.output_dir;
+    }
+    for (name, profile) in fragment.profiles {
+        config.profiles.entry(name).or_default().extend(profile);
+    }
+    config.builtin_tools.extend(fragment.builtin_tools);
+
+    let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+    stack.push((canonical, path.clone()));
+    for include in includes {
+        let pattern = dir.join(&include.path);
+        let files = fs.glob(&pattern).into_diagnostic().wrap_err_with(|| {
+            format!("invalid include '{}' in {}", include.path, path.display())
+        })?;
+        if files.is_empty() {
+            if include.path.contains(['*', '?', '[']) {
+                tracing::warn!(
+                    target: crate::check::POLICY,
+                    "include '{}' in {} does not match any file",
+                    include.path,
+                    path.display()
+                );
+                continue;
             }
+            return Err(crate::format_err!(
+                "included file {} not found, included from {}",
+                pattern.display(),
+                path.display()
+            ));
+        }
+        for file in files {
+            load_config(fs, &file, stack, visited, config)?;
         }
     }
+    stack.pop();
+    Ok(())
 }
 
 #[derive(Clone, Serialize)]
@@ -46,6 +138,16 @@ pub struct ResolvedAgents {
     pub sources: KdlSources,
     #[serde(skip)]
     pub has_local: bool,
+    /// `output-dir` from the local kg.kdl layers
+    pub local_output_dir: Option<PathBuf>,
+    /// `output-dir` from the global kg.kdl layers
+    pub global_output_dir: Option<PathBuf>,
+    /// `profile` blocks from every layer, patches of higher layers last
+    #[serde(skip)]
+    pub profiles: HashMap<String, Profile>,
+    /// Built-in tool catalog with the `builtin-tool` extensions of every layer
+    #[serde(skip)]
+    pub catalog: ToolCatalog,
 }
 
 impl Deref for ResolvedAgents {
@@ -70,7 +172,10 @@ impl Display for ResolvedAgents {
 
 /// First pass: Discover all agents from configuration files
 ///
-/// merge agent config from lowest precedence to higher precedence:
+/// Agents are declared in the kg.kdl of any layer. Each layer contributes its
+/// `<agent-name>.kdl` file and its inline kg.kdl definition, merged from lowest
+/// to highest precedence, after its `when` blocks are evaluated against
+/// `facts`. For the default global and local layers:
 /// ```text
 /// * `~/.kiro/generators/<agent-name>.kdl`
 /// * `~/.kiro/generators/kg.kdl`
@@ -82,23 +187,21 @@ pub fn discover(
     fs: &Fs,
     location: &ConfigLocation,
     format: &crate::output::OutputFormat,
+    facts: &Facts,
 ) -> Result<ResolvedAgents> {
     location.is_valid(fs)?;
 
-    let global_path = location.global_kg();
-    let local_path = location.local_kg();
-    let global_agents: GeneratorConfig = load_inline(fs, global_path)?;
-    let local_agents: GeneratorConfig = load_inline(fs, local_path)?;
-    tracing::debug!("found {} local agents", local_agents.agents.len());
-
-    let local_names: HashSet<String> =
-        HashSet::from_iter(local_agents.agents.keys().map(|k| k.to_string()));
-    let global_names: HashSet<String> =
-        HashSet::from_iter(global_agents.agents.keys().map(|k| k.to_string()));
-    let mut all_agents_names: HashSet<String> =
-        HashSet::with_capacity(global_names.len() + local_names.len());
-    all_agents_names.extend(local_names.clone());
-    all_agents_names.extend(global_names);
+    let mut layers: Vec<(ConfigLayer, GeneratorConfig)> = Vec::new();
+    for layer in location.layers() {
+        let config = load_inline(fs, layer.kg())?;
+        tracing::debug!("found {} agents in layer {layer}", config.agents.len());
+        layers.push((layer, config));
+    }
+
+    let mut all_agents_names: HashSet<String> = HashSet::new();
+    for (_, config) in &layers {
+        all_agents_names.extend(config.agents.keys().cloned());
+    }
 
     let mut resolved_agents: HashMap<String, KdlAgent> =
         HashMap::with_capacity(all_agents_names.len());
@@ -109,63 +212,79 @@ pub fn discover(
         let _enter = span.enter();
         tracing::trace!("matching location");
 
-        match location {
-            ConfigLocation::Local => {
-                resolved_agents.insert(
-                    name.to_string(),
-                    process_local(fs, name, location, local_agents.get(name), agent_sources)?,
+        // lowest to highest precedence
+        let mut parts: Vec<KdlAgent> = Vec::new();
+        for (layer, config) in &layers {
+            if let Some(file) = KdlAgentDoc::from_path(fs, name, layer.agent(name)) {
+                let path = layer.agent(name);
+                let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+                agent_sources.push(KdlAgentSource::file(layer, path));
+                parts.push(
+                    KdlAgent::from(file?)
+                        .evaluate(facts)
+                        .scripts_relative_to(fs, &dir),
                 );
             }
-            ConfigLocation::Both(_) => {
-                let mut result =
-                    process_local(fs, name, location, local_agents.get(name), agent_sources)?;
-                if let Some(a) = global_agents.get(name) {
-                    agent_sources.push(KdlAgentSource::GlobalInline);
-                    result = result.merge(a.clone());
-                }
-                let maybe_global_file = KdlAgentDoc::from_path(fs, name, location.global(name));
-                if let Some(global) = maybe_global_file {
-                    agent_sources.push(KdlAgentSource::GlobalFile(location.global(name)));
-                    result = result.merge(KdlAgent::from(global?.clone()));
-                }
-                resolved_agents.insert(name.to_string(), result);
+            if let Some(inline) = config.get(name) {
+                let file = config
+                    .files
+                    .get(name)
+                    .cloned()
+                    .unwrap_or_else(|| layer.kg());
+                let dir = file.parent().map(Path::to_path_buf).unwrap_or_default();
+                agent_sources.push(match config.matrix.get(name) {
+                    Some(origin) => KdlAgentSource::matrix(layer, file, origin.clone()),
+                    None => KdlAgentSource::inline(layer, file),
+                });
+                parts.push(inline.clone().evaluate(facts).scripts_relative_to(fs, &dir));
             }
-            ConfigLocation::Global(_) => {
-                let mut global_file = match KdlAgentDoc::from_path(fs, name, location.global(name))
-                {
-                    None => KdlAgent::new(name.to_string()),
-                    Some(a) => {
-                        agent_sources.push(KdlAgentSource::GlobalFile(location.global(name)));
-                        KdlAgent::from(a?)
-                    }
-                };
-                if let Some(inline) = global_agents.get(name) {
-                    agent_sources.push(KdlAgentSource::GlobalInline);
-                    global_file = global_file.merge(inline.clone());
-                }
-                resolved_agents.insert(name.to_string(), global_file);
-            }
-        };
+        }
+        let agent = parts
+            .into_iter()
+            .rev()
+            .reduce(|higher, lower| higher.merge(lower))
+            .unwrap_or_else(|| KdlAgent::new(name.to_string()));
+        resolved_agents.insert(name.to_string(), agent);
     }
     if let Err(e) = format.sources(&sources) {
         tracing::error!("Failed to format sources: {}", e);
     }
-    let has_local = sources.values().flatten().any(|s| {
-        matches!(
-            s,
-            KdlAgentSource::LocalFile(_) | KdlAgentSource::LocalInline
-        )
-    });
+    let has_local = sources.values().flatten().any(|s| s.local);
+    // the highest layer that sets output-dir wins
+    let output_dir = |local: bool| {
+        layers
+            .iter()
+            .rev()
+            .filter(|(l, _)| l.local == local)
+            .find_map(|(_, c)| c.output_dir.clone())
+    };
+    let mut profiles: HashMap<String, Profile> = HashMap::new();
+    for (_, config) in &layers {
+        for (name, profile) in &config.profiles {
+            profiles
+                .entry(name.clone())
+                .or_default()
+                .extend(profile.clone().evaluate(facts));
+        }
+    }
+    let mut catalog = ToolCatalog::default();
+    for (_, config) in &layers {
+        catalog.extend(config.builtin_tools.iter().cloned());
+    }
     Ok(ResolvedAgents {
         agents: resolved_agents,
+        profiles,
+        catalog,
         sources,
         has_local,
+        local_output_dir: output_dir(true),
+        global_output_dir: output_dir(false),
     })
 }
 
 #[cfg(test)]
 mod tests {
-    use {super::*, crate::os::ACTIVE_USER_HOME, std::path::PathBuf};
+    use {super::*, crate::os::ACTIVE_USER_HOME};
 
     #[tokio::test]
     #[test_log::test]
@@ -175,6 +294,7 @@ mod tests {
             &fs,
             &ConfigLocation::Local,
             &crate::output::OutputFormat::Table(true),
+            &Facts::host(),
         )?;
         let agents = resolved.agents;
         let sources = resolved.sources;
@@ -200,6 +320,7 @@ mod tests {
             &fs,
             &ConfigLocation::Global(g_path.clone()),
             &crate::output::OutputFormat::Table(true),
+            &Facts::host(),
         )?;
 
         Ok(())
@@ -216,17 +337,13 @@ mod tests {
             &fs,
             &ConfigLocation::Global(g_path.clone()),
             &crate::output::OutputFormat::Table(true),
+            &Facts::host(),
         )?;
         assert_eq!(resolved.len(), 3);
         for agent_sources in resolved.sources.values() {
             for s in agent_sources {
-                assert!(
-                    matches!(
-                        s,
-                        KdlAgentSource::GlobalInline | KdlAgentSource::GlobalFile(_)
-                    ),
-                    "agent is not global"
-                )
+                assert!(!s.local, "agent is not global");
+                assert_eq!(s.layer, "global");
             }
         }
         Ok(())
@@ -254,6 +371,7 @@ mod tests {
             &fs,
             &ConfigLocation::Both(g_path.clone()),
             &crate::output::OutputFormat::Table(true),
+            &Facts::host(),
         )?;
 
         assert_eq!(resolved.len(), 3);
@@ -270,4 +388,159 @@ mod tests {
         assert!(!format!("{resolved:?}").is_empty());
         Ok(())
     }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn test_discover_layers() -> Result<()> {
+        let fs = Fs::new();
+        let system = ConfigLayer::new("system", "/etc/kiro/generators", false);
+        fs.create_dir_all(&system.dir).await.into_diagnostic()?;
+        fs.write(
+            system.kg(),
+            r#"
+            agent "base" { description "org wide"; model "org-model"; }
+            agent "org-only" { description "org only"; }
+            "#,
+        )
+        .await
+        .into_diagnostic()?;
+        let resolved = discover(
+            &fs,
+            &ConfigLocation::Local.with_base_layers(vec![system]),
+            &crate::output::OutputFormat::Table(true),
+            &Facts::host(),
+        )?;
+        assert_eq!(resolved.len(), 4);
+
+        let base = resolved.sources.get("base").unwrap();
+        assert_eq!(base.len(), 3);
+        assert_eq!(base[0].layer, "system");
+        assert!(!base[0].local);
+        assert!(base[1..].iter().all(|s| s.layer == "local"));
+        // local layer wins, system layer fills the gaps
+        let agent = resolved.get("base").unwrap();
+        assert_ne!(agent.description.as_deref(), Some("org wide"));
+        assert_eq!(agent.model.as_deref(), Some("org-model"));
+
+        assert!(!resolved.sources.is_local("org-only"));
+        assert!(resolved.sources.is_local("base"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn test_discover_local_inline_only() -> Result<()> {
+        let fs = Fs::new();
+        let layer = ConfigLayer::new("local", "/inline", true);
+        fs.create_dir_all(&layer.dir).await.into_diagnostic()?;
+        fs.write(layer.kg(), r#"agent "solo" { description "inline only"; }"#)
+            .await
+            .into_diagnostic()?;
+        let resolved = discover(
+            &fs,
+            &ConfigLocation::Layered(vec![layer]),
+            &crate::output::OutputFormat::Table(true),
+            &Facts::host(),
+        )?;
+        // without a solo.kdl the inline config is the whole agent
+        let solo = resolved.sources.get("solo").unwrap();
+        assert_eq!(solo.len(), 1);
+        assert!(matches!(&solo[0].kind, SourceKind::Inline(_)));
+        assert!(resolved.sources.is_local("solo"));
+        assert_eq!(
+            resolved.get("solo").unwrap().description.as_deref(),
+            Some("inline only")
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn test_discover_includes() -> Result<()> {
+        let fs = Fs::new();
+        let dir = PathBuf::from("/inc");
+        fs.create_dir_all(dir.join("templates").join("nested"))
+            .await
+            .into_diagnostic()?;
+        fs.write(
+            dir.join("kg.kdl"),
+            r#"include "templates/*.kdl"; agent "main" { inherits "tpl"; }"#,
+        )
+        .await
+        .into_diagnostic()?;
+        fs.write(
+            dir.join("templates").join("tpl.kdl"),
+            r#"agent "tpl" template=#true {}; include "nested/*.kdl""#,
+        )
+        .await
+        .into_diagnostic()?;
+        fs.write(
+            dir.join("templates").join("nested").join("deep.kdl"),
+            r#"agent "deep" {}; agent-matrix "dev-{lang}" lang="rust" "go" { inherits "tpl"; }"#,
+        )
+        .await
+        .into_diagnostic()?;
+
+        let layer = ConfigLayer::global(&dir);
+        let resolved = discover(
+            &fs,
+            &ConfigLocation::Layered(vec![layer]),
+            &crate::output::OutputFormat::Table(true),
+            &Facts::host(),
+        )?;
+        assert_eq!(resolved.len(), 5);
+        let deep = resolved.sources.get("deep").unwrap();
+        assert!(matches!(
+            &deep[0].kind,
+            SourceKind::Inline(p) if *p == dir.join("templates/nested/deep.kdl")
+        ));
+        let go = resolved.sources.get("dev-go").unwrap();
+        assert!(matches!(
+            &go[0].kind,
+            SourceKind::Matrix(_, origin) if origin.to_string() == "dev-{lang} lang=go"
+        ));
+        assert_eq!(resolved.get("dev-rust").unwrap().inherits, vec!["tpl"]);
+
+        // a file reached through two includes is loaded once
+        fs.write(
+            dir.join("templates").join("other.kdl"),
+            r#"include "nested/deep.kdl""#,
+        )
+        .await
+        .into_diagnostic()?;
+        #[cfg(unix)]
+        fs.symlink_sync(
+            dir.join("templates").join("nested").join("deep.kdl"),
+            dir.join("templates").join("link.kdl"),
+        )
+        .into_diagnostic()?;
+        let config = load_inline(&fs, dir.join("kg.kdl"))?;
+        assert_eq!(config.agents.len(), 5);
+
+        // duplicate agent names report both files
+        fs.write(
+            dir.join("templates").join("nested").join("dup.kdl"),
+            r#"agent "main" {}"#,
+        )
+        .await
+        .into_diagnostic()?;
+        let err = load_inline(&fs, dir.join("kg.kdl"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("/inc/kg.kdl"), "{err}");
+        assert!(err.contains("/inc/templates/nested/dup.kdl"), "{err}");
+
+        // cycles
+        fs.write(
+            dir.join("templates").join("nested").join("dup.kdl"),
+            r#"include "../../kg.kdl""#,
+        )
+        .await
+        .into_diagnostic()?;
+        let err = load_inline(&fs, dir.join("kg.kdl"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("include cycle detected"), "{err}");
+        Ok(())
+    }
 }
@@ -0,0 +1,184 @@
+use {super::*, std::fmt::Display};
+
+/// How a generated file differs from what `kg generate` would do
+#[derive(Clone, Copy, Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum DriftKind {
+    Missing,
+    /// The file exists with other content
+    Changed,
+    /// kg wrote the file before but no longer generates it
+    Stale,
+}
+
+/// A generated file that does not match what `kg generate` would write
+#[derive(Clone, Debug, Serialize, PartialEq, Eq)]
+pub struct Drift {
+    pub path: PathBuf,
+    pub kind: DriftKind,
+}
+
+impl Drift {
+    pub fn missing(path: PathBuf) -> Self {
+        Self {
+            path,
+            kind: DriftKind::Missing,
+        }
+    }
+
+    pub fn changed(path: PathBuf) -> Self {
+        Self {
+            path,
+            kind: DriftKind::Changed,
+        }
+    }
+
+    pub fn stale(path: PathBuf) -> Self {
+        Self {
+            path,
+            kind: DriftKind::Stale,
+        }
+    }
+}
+
+impl Display for Drift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let what = match self.kind {
+            DriftKind::Missing => "missing",
+            DriftKind::Changed => "changed",
+            DriftKind::Stale => "stale",
+        };
+        write!(f, "{} {what}", self.path.display())
+    }
+}
+
+impl Generator {
+    /// Whether `path` holds `expected`, `None` when it cannot be read
+    async fn matches(&self, path: &Path, expected: impl Fn(&str) -> bool) -> Option<bool> {
+        let content = self.fs.read_to_string(path).await.ok()?;
+        Some(expected(&content))
+    }
+
+    fn push(drift: &mut Vec<Drift>, path: PathBuf, matches: Option<bool>) {
+        match matches {
+            Some(true) => {}
+            Some(false) => drift.push(Drift::changed(path)),
+            None => drift.push(Drift::missing(path)),
+        }
+    }
+
+    /// Agent files and hook scripts of `results` that are missing or differ
+    /// from what `kg generate` would write, and the ones kg wrote before that
+    /// it would remove. Agent files are compared as agents, the order of sets
+    /// such as `tools` does not matter
+    pub async fn drift(&self, results: &[AgentResult]) -> Result<Vec<Drift>> {
+        let mut drift = Vec::new();
+        for result in results.iter().filter(|r| r.writable) {
+            let path = &result.destination;
+            let matches = self
+                .matches(path, |c| {
+                    serde_json::from_str::<Agent>(c).is_ok_and(|a| a == result.kiro_agent)
+                })
+                .await;
+            Self::push(&mut drift, path.clone(), matches);
+            let dir = self.hooks_dir(&result.agent.name);
+            for script in &result.scripts {
+                let path = dir.join(&script.file_name);
+                let matches = self.matches(&path, |c| c == script.content).await;
+                Self::push(&mut drift, path, matches);
+            }
+        }
+        // with a filter the other agents were not looked at
+        if self.filter.is_empty() {
+            let stale = self.stale_agent_files(results);
+            drift.extend(
+                stale
+                    .into_iter()
+                    .chain(self.stale_scripts(results))
+                    .map(Drift::stale),
+            );
+        }
+        Ok(drift)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn test_drift() -> Result<()> {
+        let fs = Fs::new();
+        fs.write(
+            PathBuf::from(".kiro").join("generators").join("kg.kdl"),
+            r#"agent "dev" {
+                hook {
+                    stop "report" {
+                        script """
+                            #!/bin/sh
+                            echo done
+                            """
+                    }
+                }
+            }
+            agent "ops" {}"#,
+        )
+        .await
+        .into_diagnostic()?;
+        let generator = || {
+            Generator::new(
+                fs.clone(),
+                ConfigLocation::Local,
+                crate::output::OutputFormat::Table(false),
+                &crate::config::Facts::host(),
+            )
+        };
+
+        let results = generator()?.write_all(true).await?;
+        let drift = generator()?.drift(&results).await?;
+        assert_eq!(drift.len(), 3);
+        assert!(drift.iter().all(|d| d.kind == DriftKind::Missing));
+
+        generator()?.write_all(false).await?;
+        let results = generator()?.write_all(true).await?;
+        assert!(generator()?.drift(&results).await?.is_empty());
+
+        let ops = PathBuf::from(".kiro").join("agents").join("ops.json");
+        fs.write(&ops, "{}").await.into_diagnostic()?;
+        let drift = generator()?.drift(&results).await?;
+        assert_eq!(drift, vec![Drift::changed(ops.clone())]);
+        assert_eq!(drift[0].to_string(), format!("{} changed", ops.display()));
+
+        // removing an agent leaves its file and scripts behind until the next
+        // generate
+        generator()?.write_all(false).await?;
+        fs.write(
+            PathBuf::from(".kiro").join("generators").join("kg.kdl"),
+            r#"agent "ops" {}"#,
+        )
+        .await
+        .into_diagnostic()?;
+        let results = generator()?.write_all(true).await?;
+        let mut drift = generator()?.drift(&results).await?;
+        drift.sort_by(|a, b| a.path.cmp(&b.path));
+        let dev = PathBuf::from(".kiro").join("agents").join("dev.json");
+        let report = PathBuf::from(".kiro")
+            .join("hooks")
+            .join("dev-stop-report.sh");
+        assert_eq!(drift, vec![
+            Drift::stale(dev.clone()),
+            Drift::stale(report.clone())
+        ]);
+        generator()?.write_all(false).await?;
+        assert!(!fs.exists(&dev));
+        assert!(!fs.exists(&report));
+        let results = generator()?.write_all(true).await?;
+        assert!(generator()?.drift(&results).await?.is_empty());
+
+        // agent files kg did not write are left alone
+        fs.write(&dev, "{}").await.into_diagnostic()?;
+        assert!(generator()?.drift(&results).await?.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,281 @@
+use {
+    super::*,
+    crate::agent::hook::HookTrigger,
+    std::{ffi::OsStr, fmt::Display},
+};
+
+/// Shell builtins and keywords a hook command may start with, they are not
+/// looked up on `PATH`
+const SHELL_BUILTINS: &[&str] = &[
+    ".", ":", "[", "alias", "cd", "command", "echo", "eval", "exec", "exit", "export", "false",
+    "for", "if", "printf", "read", "set", "source", "test", "true", "type", "unset", "while",
+];
+
+#[derive(Clone, Copy, Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum CommandStatus {
+    Found,
+    NotFound,
+    NotExecutable,
+}
+
+/// Where an MCP server or hook command resolves to. Commands are never run
+#[derive(Clone, Debug, Serialize)]
+pub struct CommandCheck {
+    /// `mcp cargo` or `hook agentSpawn git-status`
+    pub source: String,
+    /// The executable looked up, a hook command has one per command it runs
+    pub command: String,
+    pub path: Option<PathBuf>,
+    pub status: CommandStatus,
+}
+
+impl CommandCheck {
+    pub fn is_ok(&self) -> bool {
+        self.status == CommandStatus::Found
+    }
+}
+
+impl Display for CommandCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = self
+            .path
+            .as_ref()
+            .map(|p| format!(" {}", p.display()))
+            .unwrap_or_default();
+        match self.status {
+            CommandStatus::Found => write!(f, "found{path}"),
+            CommandStatus::NotFound => write!(f, "not found"),
+            CommandStatus::NotExecutable => write!(f, "not executable{path}"),
+        }
+    }
+}
+
+/// The executable a simple command runs, skipping `VAR=value` assignments.
+/// `None` for shell builtins
+fn executable(command: &str) -> Option<&str> {
+    command
+        .split_whitespace()
+        .find(|w| !w.contains('=') || w.starts_with(['/', '.', '~']))
+        .filter(|w| !SHELL_BUILTINS.contains(w))
+}
+
+/// The executables a hook command runs, one per command of a `&&`, `||`,
+/// `;` or `|` list, each once
+fn executables(command: &str) -> Vec<&str> {
+    let mut found = Vec::new();
+    let segments = command
+        .split(';')
+        .flat_map(|s| s.split("&&"))
+        .flat_map(|s| s.split('|'));
+    for executable in segments.filter_map(executable) {
+        if !found.contains(&executable) {
+            found.push(executable);
+        }
+    }
+    found
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_metadata: &std::fs::Metadata) -> bool {
+    true
+}
+
+/// Names `command` may have on disk, Windows also tries `PATHEXT`
+fn candidates(command: &str) -> Vec<String> {
+    let mut names = vec![command.to_string()];
+    if cfg!(windows) && Path::new(command).extension().is_none() {
+        let exts = std::env::var("PATHEXT").unwrap_or_else(|_| ".EXE;.CMD;.BAT".into());
+        names.extend(
+            exts.split(';')
+                .map(|e| format!("{command}{}", e.to_lowercase())),
+        );
+    }
+    names
+}
+
+impl Generator {
+    async fn stat(&self, path: &Path) -> Option<CommandStatus> {
+        match self.fs.metadata(path).await {
+            Ok(m) if m.is_file() && is_executable(&m) => Some(CommandStatus::Found),
+            Ok(m) if m.is_file() => Some(CommandStatus::NotExecutable),
+            _ => None,
+        }
+    }
+
+    /// Resolve `command` like a shell would: a path when it contains a
+    /// separator, otherwise the first executable match on `path_var`
+    async fn resolve_command(&self, command: &str, path_var: &OsStr) -> CommandCheck {
+        let mut check = CommandCheck {
+            source: String::new(),
+            command: command.to_string(),
+            path: None,
+            status: CommandStatus::NotFound,
+        };
+        if command.contains(['/', '\\']) {
+            let path = match (command.strip_prefix("~/"), dirs::home_dir()) {
+                (Some(rest), Some(home)) => home.join(rest),
+                _ => PathBuf::from(command),
+            };
+            if let Some(status) = self.stat(&path).await {
+                check.status = status;
+                check.path = Some(path);
+            }
+            return check;
+        }
+        for dir in std::env::split_paths(path_var) {
+            for name in candidates(command) {
+                let path = dir.join(name);
+                match self.stat(&path).await {
+                    Some(CommandStatus::Found) => {
+                        check.status = CommandStatus::Found;
+                        check.path = Some(path);
+                        return check;
+                    }
+                    // a later directory may still have an executable one
+                    Some(status) if check.path.is_none() => {
+                        check.status = status;
+                        check.path = Some(path);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        check
+    }
+
+    /// Resolve the command of every enabled MCP server and the executable of
+    /// every hook of `agent` on `PATH`, without running them
+    pub(crate) async fn check_commands(&self, agent: &KdlAgent) -> Vec<CommandCheck> {
+        let path_var = std::env::var_os("PATH").unwrap_or_default();
+        self.check_commands_in(agent, &path_var).await
+    }
+
+    async fn check_commands_in(&self, agent: &KdlAgent, path_var: &OsStr) -> Vec<CommandCheck> {
+        let mut commands: Vec<(String, &str)> = Vec::new();
+        let mut servers: Vec<_> = agent
+            .mcp
+            .iter()
+            .filter(|(_, mcp)| !mcp.disabled && !mcp.command.is_empty())
+            .collect();
+        servers.sort_by_key(|(name, _)| name.as_str());
+        for (name, mcp) in servers {
+            commands.push((format!("mcp {name}"), mcp.command.as_str()));
+        }
+        for trigger in enum_iterator::all::<HookTrigger>() {
+            for (name, hook) in agent.hook.named(&trigger) {
+                if hook.disabled {
+                    continue;
+                }
+                for executable in executables(&hook.command) {
+                    commands.push((format!("hook {trigger} {name}"), executable));
+                }
+            }
+        }
+
+        let mut checks = Vec::with_capacity(commands.len());
+        for (source, command) in commands {
+            let mut check = self.resolve_command(command, path_var).await;
+            check.source = source;
+            if !check.is_ok() {
+                tracing::warn!(
+                    target: crate::check::POLICY,
+                    "agent '{}': {} command '{}' {check}",
+                    agent.name,
+                    check.source,
+                    check.command
+                );
+            }
+            checks.push(check);
+        }
+        checks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_executable() {
+        assert_eq!(executable("git status --short"), Some("git"));
+        assert_eq!(executable("RUST_LOG=info cargo test"), Some("cargo"));
+        assert_eq!(
+            executable("./scripts/check.sh arg"),
+            Some("./scripts/check.sh")
+        );
+        assert_eq!(executable("echo hello"), None);
+        assert_eq!(executable("  "), None);
+        assert_eq!(executables("cd repo && git status"), vec!["git"]);
+        assert_eq!(
+            executables("cargo fmt --check || echo fmt; git diff 2>&1 | grep -q x"),
+            vec!["cargo", "git", "grep"]
+        );
+        assert_eq!(executables("git add . && git commit"), vec!["git"]);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    #[test_log::test]
+    async fn test_check_commands() -> Result<()> {
+        let fs = Fs::new();
+        fs.write(
+            PathBuf::from(".kiro").join("generators").join("kg.kdl"),
+            r#"agent "dev" {
+                mcp "cargo" { command "cargo-mpc"; }
+                mcp "docs" { command "docs-mcp"; }
+                mcp "off" disabled=#true { command "missing"; }
+                hook {
+                    agent-spawn "status" { command "git status"; }
+                    stop "lint" { command "./lint.sh --fix"; }
+                    stop "say" { command "echo done"; }
+                }
+            }"#,
+        )
+        .await
+        .into_diagnostic()?;
+        fs.create_dir_all("/bin").await.into_diagnostic()?;
+        fs.write("/bin/docs-mcp", "").await.into_diagnostic()?;
+        fs.write("/bin/git", "").await.into_diagnostic()?;
+        fs.write("lint.sh", "").await.into_diagnostic()?;
+        for file in ["/bin/docs-mcp", "/bin/git"] {
+            use std::os::unix::fs::PermissionsExt;
+            let Fs::Chroot(root) = &fs else {
+                unreachable!("tests use a chroot");
+            };
+            let path = root.path().join(file.trim_start_matches('/'));
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755))
+                .into_diagnostic()?;
+        }
+        let generator = Generator::new(
+            fs,
+            ConfigLocation::Local,
+            crate::output::OutputFormat::Table(false),
+            &crate::config::Facts::host(),
+        )?;
+        let dev = generator
+            .merge()?
+            .into_iter()
+            .find(|a| a.name == "dev")
+            .expect("dev");
+        let checks = generator.check_commands_in(&dev, OsStr::new("/bin")).await;
+        let status: Vec<(&str, &str, CommandStatus)> = checks
+            .iter()
+            .map(|c| (c.source.as_str(), c.command.as_str(), c.status))
+            .collect();
+        assert_eq!(status, vec![
+            ("mcp cargo", "cargo-mpc", CommandStatus::NotFound),
+            ("mcp docs", "docs-mcp", CommandStatus::Found),
+            ("hook agentSpawn status", "git", CommandStatus::Found),
+            ("hook stop lint", "./lint.sh", CommandStatus::NotExecutable),
+        ]);
+        assert_eq!(checks[1].to_string(), "found /bin/docs-mcp");
+        Ok(())
+    }
+}
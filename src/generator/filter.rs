@@ -0,0 +1,89 @@
+use {super::*, glob::Pattern};
+
+/// Agents selected on the command line by name or `--match` glob
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct AgentFilter {
+    names: HashSet<String>,
+    #[serde(skip)]
+    patterns: Vec<Pattern>,
+}
+
+impl AgentFilter {
+    pub fn new(
+        names: impl IntoIterator<Item = impl Into<String>>,
+        patterns: impl IntoIterator<Item = impl AsRef<str>>,
+    ) -> Result<Self> {
+        let patterns = patterns
+            .into_iter()
+            .map(|p| {
+                Pattern::new(p.as_ref())
+                    .into_diagnostic()
+                    .wrap_err_with(|| format!("invalid --match glob '{}'", p.as_ref()))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            names: names.into_iter().map(Into::into).collect(),
+            patterns,
+        })
+    }
+
+    /// No names or globs given, every agent is selected
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty() && self.patterns.is_empty()
+    }
+
+    pub fn matches(&self, name: impl AsRef<str>) -> bool {
+        let name = name.as_ref();
+        self.is_empty()
+            || self.names.contains(name)
+            || self.patterns.iter().any(|p| p.matches(name))
+    }
+
+    /// Fail on names that are not declared and warn about globs that match
+    /// nothing
+    pub fn check<'a>(&self, agents: impl IntoIterator<Item = &'a str> + Clone) -> Result<()> {
+        for name in &self.names {
+            if agents.clone().into_iter().any(|a| a == name) {
+                continue;
+            }
+            return Err(match crate::config::did_you_mean(name, agents) {
+                Some(s) => crate::format_err!("agent '{name}' not found, did you mean '{s}'?"),
+                None => crate::format_err!("agent '{name}' not found"),
+            });
+        }
+        for pattern in &self.patterns {
+            if !agents.clone().into_iter().any(|a| pattern.matches(a)) {
+                tracing::warn!("--match '{pattern}' does not match any agent");
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter() -> Result<()> {
+        let agents = ["rust", "rust-docs", "aws", "dependabot"];
+        let filter = AgentFilter::default();
+        assert!(filter.is_empty());
+        assert!(agents.iter().all(|a| filter.matches(a)));
+
+        let filter = AgentFilter::new(["aws"], ["rust*"])?;
+        assert!(filter.matches("aws"));
+        assert!(filter.matches("rust"));
+        assert!(filter.matches("rust-docs"));
+        assert!(!filter.matches("dependabot"));
+        filter.check(agents)?;
+
+        let err = AgentFilter::new(["rsut"], Vec::<String>::new())?
+            .check(agents)
+            .unwrap_err();
+        assert!(err.to_string().contains("did you mean 'rust'"));
+
+        assert!(AgentFilter::new(Vec::<String>::new(), ["[rust"]).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,343 @@
+use {
+    super::*,
+    crate::agent::hook::{Hook, HookTrigger},
+    std::{
+        fmt::Display,
+        process::Stdio,
+        time::{Duration, Instant},
+    },
+    tokio::io::AsyncWriteExt,
+};
+
+/// Exit code of a `preToolUse` hook that blocks the tool call
+const BLOCK_EXIT_CODE: i32 = 2;
+
+/// Outcome of running one hook with `kg hook run`
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HookRun {
+    pub name: String,
+    pub trigger: HookTrigger,
+    pub command: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub matcher: Option<String>,
+    /// Why the hook did not run, it is disabled or its matcher does not
+    /// select the tool
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skipped: Option<String>,
+    /// `None` when the hook timed out or was killed by a signal
+    pub exit_code: Option<i32>,
+    pub timed_out: bool,
+    pub duration_ms: u64,
+    pub stdout: String,
+    pub stderr: String,
+    /// stdout was cut at `max_output_size`
+    pub truncated: bool,
+}
+
+impl HookRun {
+    /// Skipped, or exited with 0 in time
+    pub fn is_ok(&self) -> bool {
+        self.skipped.is_some() || (!self.timed_out && self.exit_code == Some(0))
+    }
+}
+
+impl Display for HookRun {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(reason) = &self.skipped {
+            return write!(f, "skipped, {reason}");
+        }
+        if self.timed_out {
+            return write!(f, "timed out after {} ms", self.duration_ms);
+        }
+        match self.exit_code {
+            Some(code) => write!(f, "exit {code} in {} ms", self.duration_ms)?,
+            None => write!(f, "killed in {} ms", self.duration_ms)?,
+        }
+        if self.trigger == HookTrigger::PreToolUse && self.exit_code == Some(BLOCK_EXIT_CODE) {
+            write!(f, ", blocks the tool call")?;
+        }
+        Ok(())
+    }
+}
+
+/// The JSON event Kiro passes to a hook on stdin
+fn event(
+    trigger: HookTrigger,
+    tool: Option<&str>,
+    tool_input: &Option<serde_json::Value>,
+) -> String {
+    let cwd = std::env::current_dir().unwrap_or_default();
+    let mut event = serde_json::json!({
+        "hook_event_name": trigger.to_string(),
+        "cwd": cwd.display().to_string(),
+    });
+    if trigger.is_tool_use() {
+        event["tool_name"] = tool.into();
+        event["tool_input"] = tool_input.clone().into();
+    }
+    event.to_string()
+}
+
+/// Cut `output` at `max` bytes, on a char boundary
+fn truncate(mut output: String, max: u64) -> (String, bool) {
+    let max = usize::try_from(max).unwrap_or(usize::MAX);
+    if output.len() <= max {
+        return (output, false);
+    }
+    let mut end = max;
+    while !output.is_char_boundary(end) {
+        end -= 1;
+    }
+    output.truncate(end);
+    (output, true)
+}
+
+fn shell(command: &str) -> tokio::process::Command {
+    let mut cmd = match cfg!(windows) {
+        true => {
+            let mut cmd = tokio::process::Command::new("cmd");
+            cmd.arg("/C");
+            cmd
+        }
+        false => {
+            let mut cmd = tokio::process::Command::new("bash");
+            cmd.arg("-c");
+            cmd
+        }
+    };
+    cmd.arg(command);
+    cmd
+}
+
+/// Run `hook` like Kiro does: through the shell, with the event on stdin,
+/// killed after `timeout_ms` and stdout cut at `max_output_size`
+async fn run(name: &str, trigger: HookTrigger, hook: &Hook, event: &str) -> Result<HookRun> {
+    let mut run = HookRun {
+        name: name.to_string(),
+        trigger,
+        command: hook.command.clone(),
+        matcher: hook.matcher.clone(),
+        skipped: None,
+        exit_code: None,
+        timed_out: false,
+        duration_ms: 0,
+        stdout: String::new(),
+        stderr: String::new(),
+        truncated: false,
+    };
+    let start = Instant::now();
+    let mut child = shell(&hook.command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .into_diagnostic()
+        .wrap_err_with(|| format!("failed to start hook '{name}'"))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // a hook that never reads stdin closes it early
+        let _ = stdin.write_all(event.as_bytes()).await;
+    }
+    let timeout = Duration::from_millis(hook.effective_timeout_ms());
+    let output = tokio::time::timeout(timeout, child.wait_with_output()).await;
+    run.duration_ms = u64::try_from(start.elapsed().as_millis()).unwrap_or(u64::MAX);
+    let Ok(output) = output else {
+        run.timed_out = true;
+        return Ok(run);
+    };
+    let output = output
+        .into_diagnostic()
+        .wrap_err_with(|| format!("failed to run hook '{name}'"))?;
+    run.exit_code = output.status.code();
+    (run.stdout, run.truncated) = truncate(
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+        hook.effective_max_output_size(),
+    );
+    run.stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+    Ok(run)
+}
+
+impl Generator {
+    /// Run the `trigger` hooks of an agent, or only the one called `name`.
+    /// Tool use hooks whose `matcher` does not select `tool` are skipped.
+    /// Scripts are written to a temporary directory, `.kiro/hooks` is not
+    /// touched
+    pub async fn run_hooks(
+        &self,
+        agent_name: &str,
+        trigger: HookTrigger,
+        name: Option<&str>,
+        tool: Option<&str>,
+        tool_input: Option<&str>,
+    ) -> Result<Vec<HookRun>> {
+        let mut agent = self.merged_agent(agent_name)?;
+        let tool_input: Option<serde_json::Value> = tool_input
+            .map(serde_json::from_str)
+            .transpose()
+            .into_diagnostic()
+            .wrap_err("--tool-input is not valid JSON")?;
+        if let Some(name) = name {
+            let named = agent.hook.named(&trigger);
+            if !named.iter().any(|(n, _)| *n == name) {
+                let names = named.iter().map(|(n, _)| *n);
+                return Err(match crate::config::did_you_mean(name, names) {
+                    Some(s) => crate::format_err!(
+                        "agent '{agent_name}' has no {trigger} hook '{name}', did you mean '{s}'?"
+                    ),
+                    None => {
+                        crate::format_err!("agent '{agent_name}' has no {trigger} hook '{name}'")
+                    }
+                });
+            }
+        }
+
+        let scripts = self.render_scripts(&mut agent)?;
+        let tmp = tempfile::tempdir().into_diagnostic()?;
+        let mut managed = HashMap::new();
+        let dir = self.hooks_dir(&agent.name);
+        for script in &scripts {
+            let path = tmp.path().join(&script.file_name);
+            Fs::Real
+                .write(&path, &script.content)
+                .await
+                .into_diagnostic()?;
+            Fs::Real.set_executable(&path).await.into_diagnostic()?;
+            managed.insert(
+                dir.join(&script.file_name).display().to_string(),
+                path.display().to_string(),
+            );
+        }
+
+        let event = event(trigger, tool, &tool_input);
+        let catalog: &ToolCatalog = &self.resolved.catalog;
+        let mut runs = Vec::new();
+        for (hook_name, hook) in agent.hook.named(&trigger) {
+            if name.is_some_and(|n| n != hook_name) {
+                continue;
+            }
+            let mut hook = hook.clone();
+            if let Some(path) = managed.get(&hook.command) {
+                hook.command = path.clone();
+            }
+            let matcher = hook.matcher.as_deref().unwrap_or("*");
+            let skipped = match tool {
+                _ if hook.disabled => Some("disabled".to_string()),
+                Some(tool) if trigger.is_tool_use() && !catalog.matches(matcher, tool) => {
+                    Some(format!("matcher '{matcher}' does not select '{tool}'"))
+                }
+                _ => None,
+            };
+            if skipped.is_some() {
+                runs.push(HookRun {
+                    name: hook_name.to_string(),
+                    trigger,
+                    command: hook.command.clone(),
+                    matcher: hook.matcher.clone(),
+                    skipped,
+                    exit_code: None,
+                    timed_out: false,
+                    duration_ms: 0,
+                    stdout: String::new(),
+                    stderr: String::new(),
+                    truncated: false,
+                });
+                continue;
+            }
+            runs.push(run(hook_name, trigger, &hook, &event).await?);
+        }
+        if runs.is_empty() {
+            tracing::warn!("agent '{agent_name}' has no {trigger} hooks");
+        }
+        Ok(runs)
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn test_run_hooks() -> Result<()> {
+        let fs = Fs::new();
+        fs.write(
+            PathBuf::from(".kiro").join("generators").join("kg.kdl"),
+            r#"agent "dev" {
+                hook {
+                    agent-spawn "hello" { command "echo hello; echo oops >&2"; }
+                    agent-spawn "long" {
+                        command "printf 0123456789"
+                        max-output-size 4
+                    }
+                    agent-spawn "slow" {
+                        command "sleep 5"
+                        timeout-ms 100
+                    }
+                    agent-spawn "script" {
+                        script """
+                            #!/bin/sh
+                            exit 3
+                            """
+                    }
+                    pre-tool-use "guard" {
+                        command "grep -q '\"tool_name\":\"execute_bash\"' && exit 2"
+                        matcher "shell"
+                    }
+                    pre-tool-use "github" {
+                        command "true"
+                        matcher "@github"
+                    }
+                }
+            }"#,
+        )
+        .await
+        .into_diagnostic()?;
+        let generator = Generator::new(
+            fs,
+            ConfigLocation::Local,
+            crate::output::OutputFormat::Table(false),
+            &crate::config::Facts::host(),
+        )?;
+
+        let runs = generator
+            .run_hooks("dev", HookTrigger::AgentSpawn, None, None, None)
+            .await?;
+        let names: Vec<&str> = runs.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["hello", "long", "slow", "script"]);
+        assert_eq!(runs[0].stdout, "hello\n");
+        assert_eq!(runs[0].stderr, "oops\n");
+        assert!(runs[0].is_ok());
+        assert_eq!(runs[1].stdout, "0123");
+        assert!(runs[1].truncated);
+        assert!(runs[2].timed_out);
+        assert!(runs[2].to_string().starts_with("timed out"));
+        assert_eq!(runs[3].exit_code, Some(3));
+        assert!(!runs[3].is_ok());
+
+        let runs = generator
+            .run_hooks(
+                "dev",
+                HookTrigger::PreToolUse,
+                None,
+                Some("execute_bash"),
+                Some(r#"{"command": "rm -rf /"}"#),
+            )
+            .await?;
+        assert_eq!(runs[0].exit_code, Some(BLOCK_EXIT_CODE));
+        assert!(runs[0].to_string().ends_with("blocks the tool call"));
+        assert_eq!(
+            runs[1].skipped.as_deref(),
+            Some("matcher '@github' does not select 'execute_bash'")
+        );
+
+        let Err(err) = generator
+            .run_hooks("dev", HookTrigger::Stop, Some("helo"), None, None)
+            .await
+        else {
+            panic!("dev has no stop hooks");
+        };
+        assert!(err.to_string().contains("no stop hook 'helo'"), "{err}");
+        Ok(())
+    }
+}
@@ -1,14 +1,20 @@
-use {super::*, crate::config::KdlAgent, std::collections::HashSet};
+use {
+    super::*,
+    crate::config::{KdlAgent, Params},
+    std::collections::HashSet,
+};
 
 impl Generator {
-    /// Resolve transitive inheritance chain for an agent
-    /// Returns ordered list of parent names from base to most derived
-    #[tracing::instrument(level = "debug", skip(self))]
-    fn resolve_transitive_inheritance(
+    /// Every direct parent of `agent` in the order of `inherits`, merged
+    /// with its own ancestors. `values` are the params of
+    /// `agent`, substituted into the arguments it passes to its parents
+    #[tracing::instrument(level = "debug", skip(self, values))]
+    fn resolve_parents(
         &self,
         agent: &KdlAgent,
+        values: &Params,
         visited: &mut HashSet<String>,
-    ) -> Result<Vec<String>> {
+    ) -> Result<Vec<KdlAgent>> {
         if visited.contains(&agent.name) {
             return Err(crate::format_err!(
                 "Circular inheritance detected: {} already in chain",
@@ -17,7 +23,7 @@ impl Generator {
         }
         visited.insert(agent.name.clone());
 
-        let mut chain = Vec::new();
+        let mut parents = Vec::with_capacity(agent.inherits.len());
         for parent_name in agent.inherits.iter() {
             let parent = self
                 .resolved
@@ -25,19 +31,17 @@ impl Generator {
                 .get(parent_name)
                 .ok_or_else(|| crate::format_err!("Agent '{parent_name}' not found"))?;
 
-            let parent_chain = self.resolve_transitive_inheritance(parent, visited)?;
-            for p in parent_chain {
-                if !chain.contains(&p) {
-                    chain.push(p);
-                }
-            }
-            if !chain.contains(parent_name) {
-                chain.push(parent_name.clone());
-            }
+            let args = agent.args_for(parent_name, values);
+            let parent_values = parent.bind(Some(&agent.name), &args)?;
+            let grandparents = self.resolve_parents(parent, &parent_values, visited)?;
+            parents.push(inherit(
+                parent.clone().substitute(&parent_values),
+                grandparents,
+            ));
         }
 
         visited.remove(&agent.name);
-        Ok(chain)
+        Ok(parents)
     }
 
     /// Merge all agents with transitive inheritance resolution
@@ -48,17 +52,20 @@ impl Generator {
 
         for (name, agent) in &self.resolved.agents {
             let mut visited = HashSet::new();
-            let parents = self.resolve_transitive_inheritance(agent, &mut visited)?;
+            let values = agent.bind(None, &Params::new())?;
+            let parents = self.resolve_parents(agent, &values, &mut visited)?;
             let span = tracing::debug_span!("agent", name = ?name, parents = ?parents.len());
             let _enter = span.enter();
 
-            let mut merged = agent.clone();
-            for parent_name in parents.iter().rev() {
-                let parent =
-                    self.resolved.agents.get(parent_name).ok_or_else(|| {
-                        crate::format_err!("Parent agent '{parent_name}' not found")
-                    })?;
-                merged = merged.merge(parent.clone());
+            for conflict in agent.scalar_conflicts(&parents) {
+                tracing::warn!(target: crate::check::POLICY, "{conflict}");
+            }
+
+            let mut merged = inherit(agent.clone().substitute(&values), parents);
+            // output destination belongs to the agent, never inherited
+            merged.output = agent.output.clone();
+            if let Some(profile) = &self.profile {
+                merged = profile.apply(merged);
             }
 
             resolved_agents.insert(name.clone(), merged);
@@ -70,6 +77,20 @@ impl Generator {
     }
 }
 
+/// `agent` merged with its resolved `parents`, the last parent wins. A
+/// `merge="replace"` of a parent was applied to its own ancestors and does
+/// not drop the values of the other parents
+fn inherit(agent: KdlAgent, parents: Vec<KdlAgent>) -> KdlAgent {
+    match parents
+        .into_iter()
+        .map(KdlAgent::replace_applied)
+        .reduce(|earlier, later| later.merge(earlier))
+    {
+        Some(parents) => agent.merge(parents),
+        None => agent,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -82,6 +103,7 @@ mod tests {
             fs,
             ConfigLocation::Local,
             crate::output::OutputFormat::Table(true),
+            &crate::config::Facts::host(),
         )?;
 
         let merged = generator.merge()?;
@@ -157,4 +179,142 @@ mod tests {
 
         Ok(())
     }
+
+    const PARAMS: &str = r#"
+agent "aws-base" template=#true {
+    param "service" default="s3"
+    allowed-tools "@aws-{service}"
+}
+agent "aws-team" template=#true {
+    param "team"
+    param "service" default="ec2"
+    inherits "aws-base" service="{service}"
+    description "{team} team"
+}
+agent "infra" {
+    inherits "aws-team" team="infra"
+}
+"#;
+
+    async fn generator_with(kdl: String) -> Result<Generator> {
+        let fs = Fs::new();
+        fs.write(
+            PathBuf::from(".kiro").join("generators").join("kg.kdl"),
+            kdl,
+        )
+        .await
+        .into_diagnostic()?;
+        Generator::new(
+            fs,
+            ConfigLocation::Local,
+            crate::output::OutputFormat::Table(false),
+            &crate::config::Facts::host(),
+        )
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn test_merge_params() -> Result<()> {
+        let merged = generator_with(PARAMS.to_string()).await?.merge()?;
+        let infra = merged.iter().find(|a| a.name == "infra").unwrap();
+        assert_eq!(infra.description.as_deref(), Some("infra team"));
+        // aws-team passes its own default on to aws-base
+        assert!(infra.allowed_tools.contains("@aws-ec2"));
+        assert!(!infra.allowed_tools.contains("@aws-{service}"));
+
+        let broken =
+            format!("{PARAMS}agent \"broken\" {{ inherits \"aws-team\" service=\"ecs\"; }}");
+        let err = generator_with(broken).await?.merge().unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("agent 'broken' inherits 'aws-team' without required param team"),
+            "{err}"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn test_merge_parent_order() -> Result<()> {
+        let kdl = r#"
+agent "a" { model "fast"; }
+agent "b" { model "slow"; }
+agent "ab" { inherits "a" "b"; }
+agent "ba" { inherits "b" "a"; }
+"#;
+        for _ in 0..5 {
+            let merged = generator_with(kdl.to_string()).await?.merge()?;
+            let model = |name: &str| {
+                merged
+                    .iter()
+                    .find(|a| a.name == name)
+                    .and_then(|a| a.model.clone())
+            };
+            // the last parent wins
+            assert_eq!(model("ab").as_deref(), Some("slow"));
+            assert_eq!(model("ba").as_deref(), Some("fast"));
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn test_merge_grandparent_conflict() -> Result<()> {
+        let kdl = r#"
+agent "fast" { model "fast"; }
+agent "a" { inherits "fast"; }
+agent "b" { model "slow"; }
+agent "same" { inherits "b"; }
+agent "child" { inherits "a" "b"; }
+agent "agree" { inherits "same" "b"; }
+"#;
+        let generator = generator_with(kdl.to_string()).await?;
+        let conflicts = |name: &str| -> Result<Vec<String>> {
+            let agent = &generator.resolved.agents[name];
+            let parents = generator.resolve_parents(agent, &Params::new(), &mut HashSet::new())?;
+            Ok(agent.scalar_conflicts(&parents))
+        };
+        // a only inherits its model from fast
+        assert_eq!(conflicts("child")?, vec![
+            "parents 'a' and 'b' of 'child' set different model, using 'b'".to_string()
+        ]);
+        assert!(conflicts("agree")?.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn test_merge_replace_siblings() -> Result<()> {
+        let kdl = r#"
+agent "base" { allowed-tools "@base"; native-tool { shell { allows "ls"; }; }; }
+agent "a" { allowed-tools "write" "@fromA"; native-tool { shell { allows "git .*"; }; }; }
+agent "b" {
+    inherits "base"
+    allowed-tools merge="replace" "read"
+    native-tool {
+        shell merge="replace" { allows "cargo .*"; }
+    }
+}
+agent "child" { inherits "a" "b"; }
+agent "replaced" { inherits "a" "b"; allowed-tools merge="replace" "knowledge"; }
+"#;
+        let merged = generator_with(kdl.to_string()).await?.merge()?;
+        let get = |name: &str| merged.iter().find(|a| a.name == name).unwrap();
+        // b's replace drops what base gives b, not what a gives child
+        let child = get("child");
+        assert_eq!(
+            child.allowed_tools,
+            HashSet::from(["read", "write", "@fromA"].map(String::from))
+        );
+        assert_eq!(
+            child.get_tool_shell().allows,
+            HashSet::from(["cargo .*", "git .*"].map(String::from))
+        );
+        assert_eq!(get("b").allowed_tools, HashSet::from(["read".to_string()]));
+        assert_eq!(
+            get("replaced").allowed_tools,
+            HashSet::from(["knowledge".to_string()])
+        );
+        Ok(())
+    }
 }
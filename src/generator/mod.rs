@@ -1,22 +1,36 @@
 use {
     crate::{
         Result,
-        agent::{Agent, ToolTarget},
-        config::KdlAgent,
+        agent::{Agent, ToolCatalog, ToolTarget},
+        config::{Facts, KdlAgent, Profile},
         os::Fs,
     },
     miette::{Context, IntoDiagnostic},
     serde::Serialize,
     std::{
-        collections::{HashMap, HashSet},
+        collections::{BTreeMap, HashMap, HashSet},
         fmt::{self, Debug},
-        path::PathBuf,
+        path::{Path, PathBuf},
     },
 };
+mod agent_files;
 mod config_location;
 mod discover;
+mod drift;
+mod executables;
+mod filter;
+mod hook_run;
 mod merge;
-pub use config_location::ConfigLocation;
+mod resources;
+mod scripts;
+pub use {
+    config_location::{ConfigLayer, ConfigLocation, project_roots},
+    drift::Drift,
+    executables::CommandCheck,
+    filter::AgentFilter,
+    hook_run::HookRun,
+    resources::ResourceReport,
+};
 
 use crate::source::*;
 
@@ -24,7 +38,18 @@ pub struct AgentResult {
     pub kiro_agent: Agent,
     pub agent: KdlAgent,
     pub writable: bool,
+    /// `true` when the agent comes from the local (project) config
+    pub local: bool,
+    /// Path of the generated agent JSON file
     pub destination: PathBuf,
+    /// `tools` with `*` expanded to `@builtin` and every enabled server
+    pub tools: Vec<String>,
+    /// `--check-resources`, what each `file://` resource matches
+    pub resource_reports: Option<Vec<ResourceReport>>,
+    /// `--check-commands`, where MCP server and hook commands resolve to
+    pub command_checks: Option<Vec<CommandCheck>>,
+    /// Hook scripts written next to the agent
+    pub(crate) scripts: Vec<scripts::Script>,
 }
 
 impl AgentResult {
@@ -70,8 +95,22 @@ impl AgentResult {
 /// Main generator that orchestrates agent discovery and merging
 #[derive(Serialize)]
 pub struct Generator {
-    global_path: PathBuf,
+    layers: Vec<ConfigLayer>,
     resolved: discover::ResolvedAgents,
+    /// `--output-dir` override, takes precedence over any configured output
+    output_dir: Option<PathBuf>,
+    /// agents selected on the command line
+    filter: AgentFilter,
+    /// `--profile`, applied to every agent after inheritance
+    #[serde(skip)]
+    profile: Option<Profile>,
+    /// `--drop-disabled-refs`, remove `@server` references to disabled
+    /// servers from the generated agents
+    drop_disabled_refs: bool,
+    /// `--check-resources`, resolve `file://` resources of every agent
+    check_resources: bool,
+    /// `--check-commands`, resolve MCP server and hook commands on `PATH`
+    check_commands: bool,
     #[serde(skip)]
     fs: Fs,
     #[serde(skip)]
@@ -83,44 +122,192 @@ impl Debug for Generator {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "global_path={} exists={} local_agents={}",
-            self.global_path.display(),
-            self.fs.exists(&self.global_path),
+            "layers=[{}] local_agents={}",
+            self.layers
+                .iter()
+                .map(|l| format!("{l} exists={}", self.fs.exists(l.kg())))
+                .collect::<Vec<_>>()
+                .join(", "),
             self.resolved.has_local
         )
     }
 }
 
 impl Generator {
-    /// Create a new Generator with explicit configuration location
+    /// Create a new Generator with explicit configuration location, `when`
+    /// blocks are evaluated against `facts`
     pub fn new(
         fs: Fs,
         location: ConfigLocation,
         format: crate::output::OutputFormat,
+        facts: &Facts,
     ) -> Result<Self> {
-        let global_path = location.global_kg();
-        let resolved = discover::discover(&fs, &location, &format)?;
+        let layers = location.layers();
+        let resolved = discover::discover(&fs, &location, &format, facts)?;
         Ok(Self {
-            global_path,
+            layers,
             resolved,
+            output_dir: None,
+            filter: AgentFilter::default(),
+            profile: None,
+            drop_disabled_refs: false,
+            check_resources: false,
+            check_commands: false,
             fs,
             format,
         })
     }
 
+    /// Write every agent into `dir` instead of the configured destinations
+    pub fn with_output_dir(mut self, dir: Option<PathBuf>) -> Self {
+        self.output_dir = dir;
+        self
+    }
+
+    /// Only write and show the agents selected by `filter`. Inheritance is
+    /// still resolved against every agent
+    pub fn with_filter(mut self, filter: AgentFilter) -> Result<Self> {
+        filter.check(self.resolved.keys().map(|k| k.as_str()))?;
+        self.filter = filter;
+        Ok(self)
+    }
+
+    /// Apply the patches of the named `profile` after inheritance
+    pub fn with_profile(mut self, name: Option<String>) -> Result<Self> {
+        let Some(name) = name else {
+            return Ok(self);
+        };
+        match self.resolved.profiles.get(&name) {
+            Some(profile) => {
+                for patch in profile.patches.iter().filter(|p| !p.inherits.is_empty()) {
+                    tracing::warn!(
+                        target: crate::check::POLICY,
+                        "inherits in profile '{name}' agent '{}' is ignored",
+                        patch.name
+                    );
+                }
+                self.profile = Some(profile.clone());
+                Ok(self)
+            }
+            None => {
+                let names = self.resolved.profiles.keys().map(|k| k.as_str());
+                Err(match crate::config::did_you_mean(&name, names) {
+                    Some(s) => {
+                        crate::format_err!("profile '{name}' not found, did you mean '{s}'?")
+                    }
+                    None => crate::format_err!("profile '{name}' not found"),
+                })
+            }
+        }
+    }
+
+    /// Remove references to disabled MCP servers from tools, allowed-tools
+    /// and alias of every generated agent
+    pub fn with_drop_disabled_refs(mut self, drop: bool) -> Self {
+        self.drop_disabled_refs = drop;
+        self
+    }
+
+    /// Resolve the `file://` resources of every generated agent and report
+    /// the files and bytes they match
+    pub fn with_check_resources(mut self, check: bool) -> Self {
+        self.check_resources = check;
+        self
+    }
+
+    /// Resolve the MCP server and hook commands of every generated agent on
+    /// `PATH`, without running them
+    pub fn with_check_commands(mut self, check: bool) -> Self {
+        self.check_commands = check;
+        self
+    }
+
+    /// Name of the active `--profile`
+    pub fn profile(&self) -> Option<&str> {
+        self.profile.as_ref().map(|p| p.name.as_str())
+    }
+
+    /// Point findings about an agent at the KDL file it is defined in
+    pub fn locate(&self, mut findings: Vec<crate::check::Finding>) -> Vec<crate::check::Finding> {
+        self.resolved.sources.locate(&self.fs, &mut findings);
+        findings
+    }
+
+    /// Point an [`crate::Error::InvalidAgent`] at the KDL file the agent is
+    /// defined in, like [`Generator::locate`] does for warnings
+    pub fn locate_error(&self, mut err: miette::Report) -> miette::Report {
+        if let Some(crate::Error::InvalidAgent {
+            agent, file, line, ..
+        }) = err.downcast_mut::<crate::Error>()
+            && file.is_none()
+        {
+            let mut found = [crate::check::Finding {
+                agent: Some(agent.clone()),
+                ..crate::check::Finding::error("")
+            }];
+            self.resolved.sources.locate(&self.fs, &mut found);
+            let [found] = found;
+            *file = found.file;
+            *line = found.line;
+        }
+        err
+    }
+
     /// Check if an agent is defined in local kg.kdl
     pub fn is_local(&self, agent_name: impl AsRef<str>) -> bool {
         self.resolved.sources.is_local(agent_name)
     }
 
+    /// Resolve a configured path. `~/` and relative global paths are relative
+    /// to $HOME, relative local paths are relative to the working directory
+    fn resolve_path(&self, local: bool, path: impl AsRef<Path>) -> PathBuf {
+        let path = path.as_ref();
+        let home = dirs::home_dir();
+        match (path.strip_prefix("~"), &home) {
+            (Ok(rest), Some(h)) => h.join(rest),
+            _ if local || path.is_absolute() => path.to_path_buf(),
+            _ => home.map(|h| h.join(path)).unwrap_or(path.to_path_buf()),
+        }
+    }
+
     /// Get the destination directory for an agent (global or local)
+    ///
+    /// Precedence: `--output-dir`, `output-dir` in kg.kdl, then
+    /// `.kiro/agents` or `~/.kiro/agents`
     pub fn destination_dir(&self, agent_name: impl AsRef<str>) -> PathBuf {
-        if self.is_local(agent_name) {
-            PathBuf::from(".kiro").join("agents")
+        if let Some(dir) = &self.output_dir {
+            return dir.clone();
+        }
+        let local = self.is_local(agent_name);
+        let configured = if local {
+            &self.resolved.local_output_dir
         } else {
-            dirs::home_dir()
+            &self.resolved.global_output_dir
+        };
+        match configured {
+            Some(dir) => self.resolve_path(local, dir),
+            None if local => PathBuf::from(".kiro").join("agents"),
+            None => dirs::home_dir()
                 .map(|h| h.join(".kiro").join("agents"))
-                .unwrap_or_else(|| PathBuf::from(".kiro").join("agents"))
+                .unwrap_or_else(|| PathBuf::from(".kiro").join("agents")),
+        }
+    }
+
+    /// Get the path of the generated JSON file for an agent
+    ///
+    /// A per-agent `output` wins over `output-dir` in kg.kdl, but
+    /// `--output-dir` only keeps its file name
+    pub fn destination(&self, agent: &KdlAgent) -> PathBuf {
+        let file_name = format!("{}.json", agent.name);
+        match (&self.output_dir, &agent.output) {
+            (None, Some(output)) => self.resolve_path(self.is_local(&agent.name), output),
+            (Some(dir), Some(output)) => dir.join(
+                Path::new(output)
+                    .file_name()
+                    .map(PathBuf::from)
+                    .unwrap_or(PathBuf::from(file_name)),
+            ),
+            _ => self.destination_dir(&agent.name).join(file_name),
         }
     }
 
@@ -131,52 +318,399 @@ impl Generator {
         // If local agents exist, only write those
         let write_all_agents = self.resolved.has_local;
         for agent in agents {
-            if write_all_agents || self.is_local(&agent.name) {
+            if (write_all_agents || self.is_local(&agent.name)) && self.filter.matches(&agent.name)
+            {
                 results.push(self.write(agent, dry_run).await?);
             }
         }
+        // with a filter the other agents were not looked at
+        if !dry_run {
+            self.record_agent_files(&results, self.filter.is_empty())
+                .await?;
+        }
+        if !dry_run && self.filter.is_empty() {
+            self.prune_scripts(&results).await?;
+        }
         Ok(results)
     }
 
+    /// Resolve a single non-template agent by name without writing it
+    pub async fn kiro_agent(&self, name: impl AsRef<str>) -> Result<Agent> {
+        let agent = self.merged_agent(name.as_ref())?;
+        Ok(self.write(agent, true).await?.kiro_agent)
+    }
+
+    /// The merged definition of a single non-template agent
+    pub(crate) fn merged_agent(&self, name: &str) -> Result<KdlAgent> {
+        let Some(agent) = self.merge()?.into_iter().find(|a| a.name == name) else {
+            let names = self.resolved.keys().map(|k| k.as_str());
+            return Err(match crate::config::did_you_mean(name, names) {
+                Some(s) => crate::format_err!("agent '{name}' not found, did you mean '{s}'?"),
+                None => crate::format_err!("agent '{name}' not found"),
+            });
+        };
+        if agent.is_template() {
+            return Err(crate::format_err!(
+                "agent '{name}' is a template and is never generated"
+            ));
+        }
+        Ok(agent)
+    }
+
+    /// Write all non-template agents into a single JSON object keyed by
+    /// agent name. Nothing is written to the agent destinations
+    pub async fn write_bundle(&self, path: impl AsRef<Path>) -> Result<Vec<AgentResult>> {
+        let path = path.as_ref();
+        let mut results = self.write_all(true).await?;
+        let mut bundle = BTreeMap::new();
+        for result in results.iter_mut().filter(|r| r.writable) {
+            result.destination = path.to_path_buf();
+            bundle.insert(result.agent.name.clone(), &result.kiro_agent);
+        }
+        if let Some(dir) = path.parent()
+            && !dir.as_os_str().is_empty()
+            && !self.fs.exists(dir)
+        {
+            self.fs
+                .create_dir_all(dir)
+                .await
+                .into_diagnostic()
+                .wrap_err_with(|| format!("failed to create directory {}", dir.display()))?;
+        }
+        self.fs
+            .write(
+                path,
+                serde_json::to_string_pretty(&bundle).into_diagnostic()?,
+            )
+            .await
+            .into_diagnostic()
+            .wrap_err_with(|| format!("failed to write file {}", path.display()))?;
+        Ok(results)
+    }
+
+    /// Warn about names in tools, allowed-tools and alias that are not in
+    /// the built-in tool catalog
+    fn check_tool_names(&self, agent: &KdlAgent) {
+        let catalog: &ToolCatalog = &self.resolved.catalog;
+        for (field, names) in [
+            ("tools", agent.tools.iter().collect::<Vec<_>>()),
+            ("allowed-tools", agent.allowed_tools.iter().collect()),
+            ("alias", agent.alias.keys().collect()),
+        ] {
+            for (name, suggestion) in catalog.unknown(names) {
+                let help = suggestion
+                    .map(|s| format!(", did you mean '{s}'?"))
+                    .unwrap_or_default();
+                tracing::warn!(
+                    target: crate::check::POLICY,
+                    "agent '{}': {field} '{name}' is not a built-in tool of catalog v{}{help}",
+                    agent.name,
+                    catalog.version
+                );
+            }
+        }
+    }
+
     #[tracing::instrument(skip(dry_run), level = "info")]
     pub(crate) async fn write(&self, agent: KdlAgent, dry_run: bool) -> Result<AgentResult> {
-        let destination = self.destination_dir(&agent.name);
+        self.write_agent(agent, dry_run).await.map_err(|e| {
+            let err = self.locate_error(e);
+            if let Some(invalid @ crate::Error::InvalidAgent { .. }) = err.downcast_ref() {
+                crate::output::print_error(invalid);
+            }
+            err
+        })
+    }
+
+    async fn write_agent(&self, agent: KdlAgent, dry_run: bool) -> Result<AgentResult> {
+        let agent = if self.drop_disabled_refs && !agent.is_template() {
+            agent.drop_disabled_refs()
+        } else {
+            agent
+        };
+        // templates may reference servers their children define
+        if !agent.is_template() {
+            for dangling in agent.dangling_refs() {
+                tracing::warn!(target: crate::check::POLICY, "agent '{}': {dangling}", agent.name);
+            }
+            self.check_tool_names(&agent);
+            for (trigger, name, target) in agent.hook.misplaced() {
+                tracing::warn!(
+                    target: crate::check::POLICY,
+                    "agent '{}': {trigger} hook '{name}' is placed next to unknown hook '{target}'",
+                    agent.name
+                );
+            }
+        }
+        if let Some((trigger, names)) = agent.hook.cycles().into_iter().next() {
+            return Err(crate::Error::invalid_agent(
+                &agent.name,
+                format!(
+                    "{trigger} hooks '{}' are placed before and after each other",
+                    names.join("', '")
+                ),
+            )
+            .with_help("remove before or after from one of them")
+            .into());
+        }
+        let mut servers: Vec<String> = agent
+            .mcp
+            .iter()
+            .filter(|(_, mcp)| !mcp.disabled)
+            .map(|(name, _)| name.clone())
+            .collect();
+        servers.sort();
+        let resource_reports = match self.check_resources && !agent.is_template() {
+            true => Some(self.check_resources(&agent).await?),
+            false => None,
+        };
+        let command_checks = match self.check_commands && !agent.is_template() {
+            true => Some(self.check_commands(&agent).await),
+            false => None,
+        };
+        let mut agent = agent;
+        let scripts = match agent.is_template() {
+            true => Vec::new(),
+            false => self.render_scripts(&mut agent)?,
+        };
+        let destination = self.destination(&agent);
         let result = AgentResult {
             kiro_agent: Agent::try_from(&agent)?,
             writable: !agent.is_template(),
+            local: self.is_local(&agent.name),
             destination,
+            tools: self.resolved.catalog.expand(&agent.tools, &servers),
+            resource_reports,
+            command_checks,
+            scripts,
             agent,
         };
-        result.kiro_agent.validate()?;
-        if dry_run {
+        result
+            .kiro_agent
+            .validate(&result.agent, &self.resolved.catalog)?;
+        if dry_run || !result.writable {
             return Ok(result);
         }
-        if !self.fs.exists(&result.destination) {
+        self.write_scripts(&result.agent.name, &result.scripts)
+            .await?;
+        if let Some(dir) = result.destination.parent()
+            && !dir.as_os_str().is_empty()
+            && !self.fs.exists(dir)
+        {
             self.fs
-                .create_dir_all(&result.destination)
+                .create_dir_all(dir)
                 .await
                 .into_diagnostic()
-                .wrap_err_with(|| {
-                    format!(
-                        "failed to create directory {}",
-                        result.destination.display()
-                    )
-                })?;
-        }
-        if result.writable {
-            let out = result
-                .destination
-                .join(format!("{}.json", result.agent.name));
-
-            self.fs
-                .write(
-                    &out,
-                    serde_json::to_string_pretty(&result.kiro_agent).into_diagnostic()?,
-                )
-                .await
-                .into_diagnostic()
-                .wrap_err_with(|| format!("failed to write file {}", out.display()))?;
+                .wrap_err_with(|| format!("failed to create directory {}", dir.display()))?;
         }
+        let out = &result.destination;
+        self.fs
+            .write(
+                out,
+                serde_json::to_string_pretty(&result.kiro_agent).into_diagnostic()?,
+            )
+            .await
+            .into_diagnostic()
+            .wrap_err_with(|| format!("failed to write file {}", out.display()))?;
         Ok(result)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KG: &str = r#"
+output-dir "build/agents"
+agent "base" {
+    output "base/base.json"
+}
+agent "aws-test" {
+    inherits "base"
+}
+agent "dependabot" {
+    inherits "aws-test"
+    output "bots/dependabot.json"
+}
+agent "skeleton" template=#true {}
+profile "ci" {
+    agent "*" {
+        native-tool {
+            shell deny-by-default=#true
+        }
+    }
+    agent "depend*" {
+        allowed-tools "@github"
+    }
+}
+"#;
+
+    async fn generator() -> Result<Generator> {
+        let fs = Fs::new();
+        fs.write(PathBuf::from(".kiro").join("generators").join("kg.kdl"), KG)
+            .await
+            .into_diagnostic()?;
+        Generator::new(
+            fs,
+            ConfigLocation::Local,
+            crate::output::OutputFormat::Table(false),
+            &Facts::host(),
+        )
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn test_output_destinations() -> Result<()> {
+        let generator = generator().await?;
+        let results = generator.write_all(false).await?;
+        let aws = results.iter().find(|r| r.agent.name == "aws-test").unwrap();
+        assert!(aws.local);
+        assert_eq!(aws.destination, PathBuf::from("build/agents/aws-test.json"));
+        assert!(generator.fs.exists(&aws.destination));
+
+        let bot = results
+            .iter()
+            .find(|r| r.agent.name == "dependabot")
+            .unwrap();
+        assert_eq!(bot.destination, PathBuf::from("bots/dependabot.json"));
+        assert!(generator.fs.exists(&bot.destination));
+        assert!(generator.fs.exists("base/base.json"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn test_locate() -> Result<()> {
+        use crate::check::Finding;
+        let generator = generator().await?;
+        let mut bot = Finding::warning("agent 'dependabot': tools 'x' is not a built-in tool");
+        bot.agent = Some("dependabot".into());
+        let mut unknown = Finding::warning("agent 'nope': ...");
+        unknown.agent = Some("nope".into());
+        let lint = Finding::warning("`allow` in `shell` is deprecated").at("other.kdl", Some(4));
+        let kg = PathBuf::from(".kiro").join("generators").join("kg.kdl");
+
+        let found = generator.locate(vec![bot, unknown, lint.clone()]);
+        assert_eq!(found[0].file.as_ref(), Some(&kg));
+        assert_eq!(found[0].line, Some(9));
+        assert_eq!(
+            found[0].to_string(),
+            format!("{}:9: {}", kg.display(), found[0].message)
+        );
+        assert_eq!(found[1].file, None);
+        assert_eq!(found[2], lint);
+
+        // an error kg stops on points at the agent too
+        let err = generator.locate_error(crate::Error::invalid_agent("dependabot", "x").into());
+        let found = Finding::from_error(&err);
+        assert_eq!(found[0].agent.as_deref(), Some("dependabot"));
+        assert_eq!(found[0].file.as_ref(), Some(&kg));
+        assert_eq!(found[0].line, Some(9));
+        assert_eq!(found[0].message, "agent 'dependabot': x");
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn test_output_dir_override() -> Result<()> {
+        let generator = generator()
+            .await?
+            .with_output_dir(Some(PathBuf::from("dist")));
+        let results = generator.write_all(true).await?;
+        for r in results {
+            assert!(
+                r.destination.starts_with("dist"),
+                "{}",
+                r.destination.display()
+            );
+        }
+        let agent = generator.resolved.get("dependabot").unwrap();
+        assert_eq!(
+            generator.destination(agent),
+            PathBuf::from("dist/dependabot.json")
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn test_kiro_agent() -> Result<()> {
+        let generator = generator().await?;
+        let agent = generator.kiro_agent("dependabot").await?;
+        assert_eq!(agent.name, "dependabot");
+        assert!(!generator.fs.exists("bots/dependabot.json"));
+
+        let err = generator.kiro_agent("dependabto").await.unwrap_err();
+        assert!(err.to_string().contains("did you mean 'dependabot'"));
+        assert!(generator.kiro_agent("skeleton").await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn test_write_bundle() -> Result<()> {
+        let generator = generator().await?;
+        let results = generator.write_bundle("out/bundle.json").await?;
+        assert_eq!(results.len(), 4);
+        let content = generator
+            .fs
+            .read_to_string("out/bundle.json")
+            .await
+            .into_diagnostic()?;
+        let bundle: serde_json::Value = serde_json::from_str(&content).into_diagnostic()?;
+        let bundle = bundle.as_object().unwrap();
+        assert_eq!(bundle.len(), 3);
+        assert!(bundle.contains_key("aws-test"));
+        assert!(!generator.fs.exists("bots/dependabot.json"));
+        assert!(!generator.fs.exists("build/agents/aws-test.json"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn test_filter() -> Result<()> {
+        let generator = generator()
+            .await?
+            .with_filter(AgentFilter::new(["dependabot"], ["sk*"])?)?;
+        let results = generator.write_all(false).await?;
+        let mut names: Vec<&str> = results.iter().map(|r| r.agent.name.as_str()).collect();
+        names.sort();
+        assert_eq!(names, vec!["dependabot", "skeleton"]);
+        // inheritance is still resolved against unselected parents
+        let bot = results
+            .iter()
+            .find(|r| r.agent.name == "dependabot")
+            .unwrap();
+        assert!(bot.agent.tools.contains("*"));
+        assert!(generator.fs.exists("bots/dependabot.json"));
+        assert!(!generator.fs.exists("build/agents/aws-test.json"));
+
+        let generator = generator.with_filter(AgentFilter::new(["nope"], Vec::<String>::new())?);
+        assert!(generator.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn test_profile() -> Result<()> {
+        let generator = generator().await?;
+        assert!(generator.profile().is_none());
+        let agents = generator.merge()?;
+        let bot = agents.iter().find(|a| a.name == "dependabot").unwrap();
+        assert_eq!(bot.get_tool_shell().deny_by_default, None);
+
+        let generator = generator.with_profile(Some("ci".into()))?;
+        assert_eq!(generator.profile(), Some("ci"));
+        let agents = generator.merge()?;
+        let bot = agents.iter().find(|a| a.name == "dependabot").unwrap();
+        assert_eq!(bot.get_tool_shell().deny_by_default, Some(true));
+        assert!(bot.allowed_tools.contains("@github"));
+        let aws = agents.iter().find(|a| a.name == "aws-test").unwrap();
+        assert_eq!(aws.get_tool_shell().deny_by_default, Some(true));
+        assert!(!aws.allowed_tools.contains("@github"));
+
+        let err = generator.with_profile(Some("cj".into())).err().unwrap();
+        assert!(err.to_string().contains("did you mean 'ci'"), "{err}");
+        Ok(())
+    }
+}
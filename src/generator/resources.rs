@@ -0,0 +1,141 @@
+use {super::*, std::fmt::Display};
+
+/// What a `file://` resource pulls into the context of an agent
+#[derive(Clone, Debug, Serialize)]
+pub struct ResourceReport {
+    /// The resource as configured, e.g. `file://.amazonq/rules/**/*.md`
+    pub resource: String,
+    /// Path or glob after expanding `~` and the destination root
+    pub path: PathBuf,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+impl Display for ResourceReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = if self.files == 1 { "" } else { "s" };
+        write!(
+            f,
+            "{} ({} file{s}, {})",
+            self.resource,
+            self.files,
+            human_bytes(self.bytes)
+        )
+    }
+}
+
+fn human_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 3] = ["KiB", "MiB", "GiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.1} {}", UNITS[unit])
+}
+
+impl Generator {
+    /// Resolve every `file://` resource of `agent` against its destination
+    /// root, the project for local agents and $HOME for global ones, and
+    /// count the files and bytes it matches. Warns about resources that
+    /// match nothing
+    pub(crate) async fn check_resources(&self, agent: &KdlAgent) -> Result<Vec<ResourceReport>> {
+        let local = self.is_local(&agent.name);
+        let mut resources: Vec<&String> = agent.resources.iter().collect();
+        resources.sort();
+        let mut reports = Vec::with_capacity(resources.len());
+        for resource in resources {
+            let Some(path) = resource.strip_prefix("file://") else {
+                continue;
+            };
+            let path = self.resolve_path(local, path);
+            let matches = if path.to_string_lossy().contains(['*', '?', '[']) {
+                self.fs.glob(&path).into_diagnostic().wrap_err_with(|| {
+                    format!("invalid resource '{resource}' of agent '{}'", agent.name)
+                })?
+            } else {
+                vec![path.clone()]
+            };
+            let mut report = ResourceReport {
+                resource: resource.clone(),
+                path,
+                files: 0,
+                bytes: 0,
+            };
+            for file in matches {
+                match self.fs.metadata(&file).await {
+                    Ok(m) if m.is_file() => {
+                        report.files += 1;
+                        report.bytes += m.len();
+                    }
+                    _ => continue,
+                }
+            }
+            if report.files == 0 {
+                tracing::warn!(
+                    target: crate::check::POLICY,
+                    "agent '{}': resource '{resource}' does not match any file, looked in {}",
+                    agent.name,
+                    report.path.display()
+                );
+            }
+            reports.push(report);
+        }
+        Ok(reports)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn test_check_resources() -> Result<()> {
+        let fs = Fs::new();
+        fs.write(
+            PathBuf::from(".kiro").join("generators").join("kg.kdl"),
+            r#"agent "docs" {
+                resource "file://rules/**/*.md"
+                resource "file://MISSING.md"
+                resource "https://example.com"
+            }"#,
+        )
+        .await
+        .into_diagnostic()?;
+        fs.create_dir_all("rules/nested").await.into_diagnostic()?;
+        fs.write("rules/a.md", "a".repeat(10))
+            .await
+            .into_diagnostic()?;
+        fs.write("rules/nested/b.md", "b".repeat(2000))
+            .await
+            .into_diagnostic()?;
+        fs.write("rules/c.txt", "c").await.into_diagnostic()?;
+        let generator = Generator::new(
+            fs,
+            ConfigLocation::Local,
+            crate::output::OutputFormat::Table(false),
+            &crate::config::Facts::host(),
+        )?;
+        let docs = generator
+            .merge()?
+            .into_iter()
+            .find(|a| a.name == "docs")
+            .expect("docs");
+        let reports = generator.check_resources(&docs).await?;
+        assert_eq!(reports.len(), 2);
+        assert_eq!(reports[0].resource, "file://MISSING.md");
+        assert_eq!(reports[0].files, 0);
+        assert_eq!(reports[1].files, 2);
+        assert_eq!(reports[1].bytes, 2010);
+        assert_eq!(
+            reports[1].to_string(),
+            "file://rules/**/*.md (2 files, 2.0 KiB)"
+        );
+        Ok(())
+    }
+}
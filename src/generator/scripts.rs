@@ -0,0 +1,351 @@
+use {
+    super::*,
+    crate::agent::hook::{HookScript, HookTrigger},
+    std::collections::BTreeSet,
+};
+
+/// Scripts kg wrote into a hooks directory, per agent, so they can be
+/// removed with their hook or agent
+const MANIFEST: &str = "kg-scripts.json";
+
+type Manifest = BTreeMap<String, BTreeSet<String>>;
+
+/// A hook script to write, its file name in the hooks directory and content
+pub(crate) struct Script {
+    pub file_name: String,
+    pub content: String,
+}
+
+impl Generator {
+    /// `.kiro/hooks` next to the directory the agent is written to
+    pub fn hooks_dir(&self, agent_name: impl AsRef<str>) -> PathBuf {
+        self.destination_dir(agent_name)
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .map(|p| p.join("hooks"))
+            .unwrap_or_else(|| PathBuf::from(".kiro").join("hooks"))
+    }
+
+    /// Set the `command` of every hook with a `script` to the managed file it
+    /// is written to, `<hooks-dir>/<agent>-<trigger>-<name>.sh`. Returns the
+    /// scripts to write
+    pub(crate) fn render_scripts(&self, agent: &mut KdlAgent) -> Result<Vec<Script>> {
+        let dir = self.hooks_dir(&agent.name);
+        let mut scripts = Vec::new();
+        for trigger in enum_iterator::all::<HookTrigger>() {
+            for (name, hook) in agent.hook.hooks_mut(&trigger).iter_mut() {
+                if hook.disabled {
+                    continue;
+                }
+                let Some(script) = hook.script.take() else {
+                    continue;
+                };
+                if !hook.command.is_empty() {
+                    return Err(crate::Error::invalid_agent(
+                        &agent.name,
+                        format!("{trigger} hook '{name}' sets both command and script"),
+                    )
+                    .with_help("remove command, kg sets it to run the script")
+                    .into());
+                }
+                let content = match script {
+                    HookScript::Inline(content) => content,
+                    HookScript::File(path) => {
+                        let path = self.resolve_path(true, &path);
+                        self.fs
+                            .read_to_string_sync(&path)
+                            .into_diagnostic()
+                            .wrap_err_with(|| {
+                                format!(
+                                    "failed to read script {} of hook '{name}' of agent '{}'",
+                                    path.display(),
+                                    agent.name
+                                )
+                            })?
+                    }
+                };
+                let file_name = format!("{}-{trigger}-{name}.sh", agent.name);
+                hook.command = dir.join(&file_name).display().to_string();
+                scripts.push(Script { file_name, content });
+            }
+        }
+        scripts.sort_by(|a, b| a.file_name.cmp(&b.file_name));
+        Ok(scripts)
+    }
+
+    fn read_manifest(&self, dir: &Path) -> Manifest {
+        let path = dir.join(MANIFEST);
+        if !self.fs.exists(&path) {
+            return Manifest::new();
+        }
+        self.fs
+            .read_to_string_sync(&path)
+            .ok()
+            .and_then(|m| serde_json::from_str(&m).ok())
+            .unwrap_or_else(|| {
+                tracing::warn!("ignoring unreadable {}", path.display());
+                Manifest::new()
+            })
+    }
+
+    async fn write_manifest(&self, dir: &Path, manifest: &Manifest) -> Result<()> {
+        let path = dir.join(MANIFEST);
+        if manifest.is_empty() {
+            if self.fs.exists(&path) {
+                self.fs.remove_file(&path).await.into_diagnostic()?;
+            }
+            return Ok(());
+        }
+        self.fs
+            .write(
+                &path,
+                serde_json::to_string_pretty(manifest).into_diagnostic()?,
+            )
+            .await
+            .into_diagnostic()
+            .wrap_err_with(|| format!("failed to write file {}", path.display()))
+    }
+
+    async fn remove_scripts(&self, dir: &Path, files: impl IntoIterator<Item = &String>) {
+        for file in files {
+            let path = dir.join(file);
+            if self.fs.exists(&path) {
+                tracing::debug!("removing hook script {}", path.display());
+                if let Err(e) = self.fs.remove_file(&path).await {
+                    tracing::warn!("failed to remove {}: {e}", path.display());
+                }
+            }
+        }
+    }
+
+    /// Write the scripts of an agent, executable, and remove the ones it no
+    /// longer has
+    pub(crate) async fn write_scripts(&self, agent_name: &str, scripts: &[Script]) -> Result<()> {
+        let dir = self.hooks_dir(agent_name);
+        let mut manifest = self.read_manifest(&dir);
+        let previous = manifest.remove(agent_name).unwrap_or_default();
+        if scripts.is_empty() && previous.is_empty() {
+            return Ok(());
+        }
+        if !scripts.is_empty() && !self.fs.exists(&dir) {
+            self.fs
+                .create_dir_all(&dir)
+                .await
+                .into_diagnostic()
+                .wrap_err_with(|| format!("failed to create directory {}", dir.display()))?;
+        }
+        for script in scripts {
+            let path = dir.join(&script.file_name);
+            self.fs
+                .write(&path, &script.content)
+                .await
+                .into_diagnostic()
+                .wrap_err_with(|| format!("failed to write file {}", path.display()))?;
+            self.fs
+                .set_executable(&path)
+                .await
+                .into_diagnostic()
+                .wrap_err_with(|| format!("failed to make {} executable", path.display()))?;
+        }
+        let current: BTreeSet<String> = scripts.iter().map(|s| s.file_name.clone()).collect();
+        self.remove_scripts(&dir, previous.difference(&current))
+            .await;
+        if !current.is_empty() {
+            manifest.insert(agent_name.to_string(), current);
+        }
+        self.write_manifest(&dir, &manifest).await
+    }
+
+    /// Scripts in the manifests of the hooks directories of `results` that
+    /// kg would no longer write and that are still there
+    pub(crate) fn stale_scripts(&self, results: &[AgentResult]) -> Vec<PathBuf> {
+        let current: HashMap<&str, BTreeSet<&str>> = results
+            .iter()
+            .filter(|r| r.writable)
+            .map(|r| {
+                let files = r.scripts.iter().map(|s| s.file_name.as_str()).collect();
+                (r.agent.name.as_str(), files)
+            })
+            .collect();
+        let dirs: BTreeSet<PathBuf> = results
+            .iter()
+            .map(|r| self.hooks_dir(&r.agent.name))
+            .collect();
+        let mut stale = Vec::new();
+        for dir in dirs {
+            for (agent, files) in self.read_manifest(&dir) {
+                let kept = current.get(agent.as_str());
+                stale.extend(
+                    files
+                        .iter()
+                        .filter(|f| kept.is_none_or(|k| !k.contains(f.as_str())))
+                        .map(|f| dir.join(f))
+                        .filter(|p| self.fs.exists(p)),
+                );
+            }
+        }
+        stale
+    }
+
+    /// Remove the scripts of agents that were not generated, in every hooks
+    /// directory agents were written to
+    pub(crate) async fn prune_scripts(&self, results: &[AgentResult]) -> Result<()> {
+        let generated: HashSet<&str> = results
+            .iter()
+            .filter(|r| r.writable)
+            .map(|r| r.agent.name.as_str())
+            .collect();
+        let dirs: BTreeSet<PathBuf> = results
+            .iter()
+            .map(|r| self.hooks_dir(&r.agent.name))
+            .collect();
+        for dir in dirs {
+            let mut manifest = self.read_manifest(&dir);
+            let stale: Vec<String> = manifest
+                .keys()
+                .filter(|a| !generated.contains(a.as_str()))
+                .cloned()
+                .collect();
+            if stale.is_empty() {
+                continue;
+            }
+            for agent in stale {
+                if let Some(files) = manifest.remove(&agent) {
+                    tracing::info!("removing hook scripts of agent '{agent}'");
+                    self.remove_scripts(&dir, &files).await;
+                }
+            }
+            self.write_manifest(&dir, &manifest).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCRIPTS: &str = r#"
+agent "dev" {
+    hook {
+        agent-spawn "whoami" {
+            script "hooks/whoami.sh"
+        }
+        agent-spawn "pwd" {
+            script "pwd"
+        }
+        stop "report" {
+            script """
+                #!/bin/sh
+                echo done
+                """
+        }
+    }
+}
+"#;
+
+    async fn generator(fs: &Fs, kdl: &str) -> Result<Generator> {
+        fs.write(
+            PathBuf::from(".kiro").join("generators").join("kg.kdl"),
+            kdl,
+        )
+        .await
+        .into_diagnostic()?;
+        Generator::new(
+            fs.clone(),
+            ConfigLocation::Local,
+            crate::output::OutputFormat::Table(false),
+            &crate::config::Facts::host(),
+        )
+    }
+
+    #[tokio::test]
+    #[test_log::test]
+    async fn test_hook_scripts() -> Result<()> {
+        let fs = Fs::new();
+        let hooks = PathBuf::from(".kiro").join("hooks");
+        fs.create_dir_all(".kiro/generators/hooks")
+            .await
+            .into_diagnostic()?;
+        fs.write(".kiro/generators/hooks/whoami.sh", "#!/bin/sh\nwhoami\n")
+            .await
+            .into_diagnostic()?;
+
+        let results = generator(&fs, SCRIPTS).await?.write_all(false).await?;
+        let dev = results.iter().find(|r| r.agent.name == "dev").unwrap();
+        let spawn = &dev.kiro_agent.hooks[&HookTrigger::AgentSpawn];
+        let whoami = hooks.join("dev-agentSpawn-whoami.sh");
+        assert_eq!(spawn[0].command, whoami.display().to_string());
+        assert_eq!(
+            fs.read_to_string(&whoami).await.into_diagnostic()?,
+            "#!/bin/sh\nwhoami\n"
+        );
+        let pwd = hooks.join("dev-agentSpawn-pwd.sh");
+        assert_eq!(spawn[1].command, pwd.display().to_string());
+        assert_eq!(fs.read_to_string(&pwd).await.into_diagnostic()?, "pwd");
+        let report = hooks.join("dev-stop-report.sh");
+        assert_eq!(
+            fs.read_to_string(&report).await.into_diagnostic()?,
+            "#!/bin/sh\necho done"
+        );
+        // script-file is always a file, a script naming no file is the script
+        let file = SCRIPTS.replace(
+            "script \"hooks/whoami.sh\"",
+            "script-file \"hooks/whoami.sh\"",
+        );
+        generator(&fs, &file).await?.write_all(false).await?;
+        assert_eq!(
+            fs.read_to_string(&whoami).await.into_diagnostic()?,
+            "#!/bin/sh\nwhoami\n"
+        );
+        let missing = SCRIPTS.replace("hooks/whoami.sh", "hooks/who.sh");
+        generator(&fs, &missing).await?.write_all(false).await?;
+        assert_eq!(
+            fs.read_to_string(&whoami).await.into_diagnostic()?,
+            "hooks/who.sh"
+        );
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs.metadata(&report).await.into_diagnostic()?.permissions();
+            assert_eq!(mode.mode() & 0o111, 0o111);
+        }
+
+        // a removed hook takes its script along, a removed agent all of them
+        let one = SCRIPTS.replace("script \"hooks/whoami.sh\"", "command \"whoami\"");
+        generator(&fs, &one).await?.write_all(false).await?;
+        assert!(!fs.exists(&whoami));
+        assert!(fs.exists(&report));
+        generator(&fs, "agent \"other\" {}")
+            .await?
+            .write_all(false)
+            .await?;
+        assert!(!fs.exists(&report));
+        assert!(!fs.exists(hooks.join(MANIFEST)));
+
+        let both = SCRIPTS.replace("script \"hooks/whoami.sh\"", "script \"x\"; command \"y\"");
+        let Err(err) = generator(&fs, &both).await?.write_all(true).await else {
+            panic!("command and script are exclusive");
+        };
+        assert!(
+            err.to_string().contains("sets both command and script"),
+            "{err}"
+        );
+
+        let cycle = r#"agent "dev" {
+            hook {
+                stop "a" before="b" { command "a"; }
+                stop "b" before="a" { command "b"; }
+            }
+        }"#;
+        let Err(err) = generator(&fs, cycle).await?.write_all(true).await else {
+            panic!("a and b cannot both run first");
+        };
+        assert!(
+            err.to_string()
+                .contains("stop hooks 'a', 'b' are placed before and after each other"),
+            "{err}"
+        );
+        Ok(())
+    }
+}
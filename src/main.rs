@@ -1,4 +1,5 @@
 mod agent;
+mod check;
 mod commands;
 mod config;
 mod error;
@@ -13,17 +14,27 @@ use {
     crate::{generator::Generator, os::Fs},
     clap::Parser,
     miette::{Context, IntoDiagnostic},
-    std::path::Path,
+    std::{path::Path, process::ExitCode},
     tracing::{debug, enabled},
     tracing_error::ErrorLayer,
-    tracing_subscriber::prelude::*,
+    tracing_subscriber::{
+        filter::{LevelFilter, filter_fn},
+        prelude::*,
+    },
 };
 pub use {error::Error, miette::miette as format_err};
 pub type Result<T> = miette::Result<T>;
 
 pub(crate) const DOCS_URL: &str = "https://kg.cartera-mesh.com";
 
-fn init_tracing(debug: bool, trace_agent: Option<&str>) {
+/// `findings` collects every warning, for `kg check`. With `check` set to the
+/// color choice, warnings are left to the summary of `kg check`
+fn init_tracing(
+    debug: bool,
+    trace_agent: Option<&str>,
+    findings: check::Findings,
+    check: Option<bool>,
+) {
     let filter = if let Some(agent) = trace_agent {
         let directive = if agent == "all" {
             "trace".to_string()
@@ -38,28 +49,38 @@ fn init_tracing(debug: bool, trace_agent: Option<&str>) {
             .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info"))
     };
 
+    let findings = findings.with_filter(filter_fn(check::Findings::interested));
+    // the error layer only takes spans, an unfiltered layer would enable every
+    // `enabled!(DEBUG)` check whatever the fmt filter is
     if debug {
         tracing_subscriber::registry()
-            .with(filter)
+            .with(findings)
             .with(
                 tracing_subscriber::fmt::layer()
                     .with_level(true)
                     .with_writer(std::io::stderr)
-                    .with_target(true),
+                    .with_target(true)
+                    .with_filter(filter),
             )
-            .with(ErrorLayer::default())
+            .with(ErrorLayer::default().with_filter(filter_fn(|meta| meta.is_span())))
             .init();
     } else {
         tracing_subscriber::registry()
-            .with(filter)
+            .with(findings)
             .with(
                 tracing_subscriber::fmt::layer()
                     .without_time()
                     .with_target(false)
                     .with_level(true)
-                    .with_writer(std::io::stderr),
+                    .with_ansi(check.unwrap_or(true))
+                    .with_writer(std::io::stderr)
+                    .with_filter(match check {
+                        Some(_) => LevelFilter::ERROR,
+                        None => LevelFilter::TRACE,
+                    })
+                    .with_filter(filter),
             )
-            .with(ErrorLayer::default())
+            .with(ErrorLayer::default().with_filter(filter_fn(|meta| meta.is_span())))
             .init();
     }
 }
@@ -121,13 +142,31 @@ async fn init(fs: &Fs, gen_dir: impl AsRef<Path>) -> Result<()> {
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> Result<ExitCode> {
     let cli = commands::Cli::parse();
     if matches!(cli.command, commands::Command::Version) {
         println!("{}", clap::crate_version!());
-        return Ok(());
+        return Ok(ExitCode::SUCCESS);
+    }
+    let findings = check::Findings::default();
+    let check = matches!(cli.command, commands::Command::Check(_)).then(|| cli.color());
+    init_tracing(cli.debug, cli.trace.as_deref(), findings.clone(), check);
+    let format = cli.format_color();
+    let result = run(cli, &findings).await;
+    // CI still gets a report to annotate when kg stops on a config error
+    if let Err(err) = &result
+        && matches!(
+            format,
+            output::OutputFormat::Sarif | output::OutputFormat::Junit
+        )
+    {
+        format.check(&check::CheckReport::failed(err, findings.take()))?;
     }
-    init_tracing(cli.debug, cli.trace.as_deref());
+    result
+}
+
+/// The exit code is the status of `kg check`, success for the other commands
+async fn run(cli: commands::Cli, findings: &check::Findings) -> Result<ExitCode> {
     let span = tracing::info_span!(
         "main",
         dry_run = tracing::field::Empty,
@@ -138,6 +177,10 @@ async fn main() -> Result<()> {
     let local_mode = cli.is_local();
     let global_mode = cli.is_global();
     let (home_dir, home_config) = cli.config()?;
+    let output_dir = cli.output_dir();
+    let bundle = cli.bundle();
+    let filter = cli.agent_filter()?;
+    let facts = cli.facts()?;
     let fs = Fs::new();
 
     if let commands::Command::Init(args) = &cli.command {
@@ -145,9 +188,11 @@ async fn main() -> Result<()> {
             Some(path) => path.clone(),
             None => home_dir.join(".kiro").join("generators"),
         };
-        return init(&fs, dir).await;
+        init(&fs, dir).await?;
+        return Ok(ExitCode::SUCCESS);
     }
 
+    let mut project_roots = Vec::new();
     if global_mode {
         debug!(
             "changing working directory to {}",
@@ -156,6 +201,22 @@ async fn main() -> Result<()> {
         std::env::set_current_dir(&home_dir)
             .into_diagnostic()
             .wrap_err(format!("failed to set CWD {}", home_dir.display()))?;
+    } else {
+        // run from anywhere inside the project, like git
+        let cwd = std::env::current_dir().into_diagnostic()?;
+        let home = std::fs::canonicalize(&home_dir).unwrap_or(home_dir.clone());
+        project_roots = generator::project_roots(&fs, &cwd, Some(&home));
+        match project_roots.first() {
+            Some(root) => {
+                debug!("project root {}", root.display());
+                if *root != cwd {
+                    std::env::set_current_dir(root)
+                        .into_diagnostic()
+                        .wrap_err(format!("failed to set CWD {}", root.display()))?;
+                }
+            }
+            None => debug!("no .kiro/generators/kg.kdl found from {}", cwd.display()),
+        }
     }
     if local_mode {
         span.record("local_mode", true);
@@ -172,10 +233,20 @@ async fn main() -> Result<()> {
     } else {
         // Default: merge both global and local
         generator::ConfigLocation::Both(home_config)
-    };
+    }
+    .with_base_layers(cli.base_layers(&fs))
+    .with_project_layers(project_roots.get(1..).unwrap_or_default())
+    .without_global_duplicates(&std::env::current_dir().into_diagnostic()?);
+    debug!("config layers {location}");
 
     let format = cli.format_color();
-    let q_generator_config: Generator = Generator::new(fs, location, format)?;
+    let q_generator_config: Generator = Generator::new(fs, location, format, &facts)?
+        .with_output_dir(output_dir)
+        .with_filter(filter)?
+        .with_profile(cli.profile())?
+        .with_drop_disabled_refs(cli.drop_disabled_refs())
+        .with_check_resources(cli.check_resources())
+        .with_check_commands(cli.check_commands());
     if enabled!(tracing::Level::TRACE) {
         tracing::trace!(
             "Loaded Agent Generator Config:\n{}",
@@ -185,15 +256,70 @@ async fn main() -> Result<()> {
         );
     }
 
-    match cli.command {
-        commands::Command::Validate(args) | commands::Command::Generate(args) => {
+    match (cli.command, bundle) {
+        (
+            commands::Command::Generate(commands::GenerateArgs {
+                stdout: Some(name), ..
+            }),
+            _,
+        ) => {
+            let agent = q_generator_config.kiro_agent(&name).await?;
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&agent)
+                    .into_diagnostic()
+                    .wrap_err_with(|| format!("unable to serialize agent {name}"))?
+            );
+        }
+        (commands::Command::Generate(commands::GenerateArgs { args, .. }), Some(bundle)) => {
+            let results = q_generator_config.write_bundle(&bundle).await?;
+            format.result(
+                dry_run,
+                args.show_templates,
+                q_generator_config.profile(),
+                results,
+                q_generator_config.locate(findings.take()),
+            )?;
+        }
+        (commands::Command::Validate(args), _)
+        | (commands::Command::Generate(commands::GenerateArgs { args, .. }), None) => {
             let results = q_generator_config.write_all(dry_run).await?;
-            format.result(dry_run, args.show_templates, results)?;
+            format.result(
+                dry_run,
+                args.show_templates,
+                q_generator_config.profile(),
+                results,
+                q_generator_config.locate(findings.take()),
+            )?;
+        }
+        (commands::Command::Check(_), _) => {
+            let results = q_generator_config.write_all(true).await?;
+            let drift = q_generator_config.drift(&results).await?;
+            let findings = q_generator_config.locate(findings.take());
+            let report = check::CheckReport::new(&results, findings, drift);
+            format.check(&report)?;
+            return Ok(ExitCode::from(report.exit_code()));
+        }
+        (commands::Command::Hook(commands::HookCommand::Run(args)), _) => {
+            let runs = q_generator_config
+                .run_hooks(
+                    &args.agent,
+                    args.trigger,
+                    args.name.as_deref(),
+                    args.tool.as_deref(),
+                    args.tool_input.as_deref(),
+                )
+                .await?;
+            format.hook_runs(&runs)?;
+            let failed = runs.iter().filter(|r| !r.is_ok()).count();
+            if failed > 0 {
+                return Err(format_err!("{failed} of {} hooks failed", runs.len()));
+            }
         }
         _ => {}
     };
 
-    Ok(())
+    Ok(ExitCode::SUCCESS)
 }
 
 #[cfg(test)]
@@ -444,6 +444,40 @@ impl Fs {
         }
     }
 
+    /// Queries the file system metadata for a path, following symlinks.
+    ///
+    /// This is a proxy to [`tokio::fs::metadata`]
+    pub async fn metadata(&self, path: impl AsRef<Path>) -> io::Result<std::fs::Metadata> {
+        match self {
+            Self::Real => fs::metadata(path).await,
+            Self::Chroot(root) => fs::metadata(append(root.path(), path)).await,
+            Self::Fake(_) => panic!("unimplemented"),
+        }
+    }
+
+    /// Makes a file executable by everyone who can read it. Does nothing on
+    /// Windows, where any file can be run
+    pub async fn set_executable(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let path = match self {
+                Self::Real => path.as_ref().to_path_buf(),
+                Self::Chroot(root) => append(root.path(), path),
+                Self::Fake(_) => panic!("unimplemented"),
+            };
+            let mut permissions = fs::metadata(&path).await?.permissions();
+            let mode = permissions.mode();
+            permissions.set_mode(mode | ((mode & 0o444) >> 2));
+            fs::set_permissions(path, permissions).await
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = path;
+            Ok(())
+        }
+    }
+
     /// Reads a symbolic link, returning the file that the link points to.
     ///
     /// This is a proxy to [`tokio::fs::read_link`].
@@ -458,6 +492,57 @@ impl Fs {
         }
     }
 
+    /// Returns the absolute path with symlinks, `.` and `..` resolved.
+    ///
+    /// This is a proxy to [`std::fs::canonicalize`].
+    pub fn canonicalize(&self, path: impl AsRef<Path>) -> io::Result<PathBuf> {
+        match self {
+            Self::Real => std::fs::canonicalize(path),
+            Self::Chroot(root) => {
+                let root = std::fs::canonicalize(root.path())?;
+                let path = std::fs::canonicalize(append(&root, path))?;
+                match path.strip_prefix(&root) {
+                    Ok(relative) => Ok(Path::new("/").join(relative)),
+                    Err(_) => Ok(path),
+                }
+            }
+            Self::Fake(_) => panic!("unimplemented"),
+        }
+    }
+
+    /// Returns the paths matching a glob pattern, in alphabetical order.
+    ///
+    /// This is a proxy to [`glob::glob`].
+    pub fn glob(&self, pattern: impl AsRef<Path>) -> io::Result<Vec<PathBuf>> {
+        fn run(pattern: &Path) -> io::Result<Vec<PathBuf>> {
+            let Some(pattern) = pattern.to_str() else {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "glob pattern is not valid UTF-8",
+                ));
+            };
+            glob::glob(pattern)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?
+                .map(|p| p.map_err(io::Error::from))
+                .collect()
+        }
+        let pattern = pattern.as_ref();
+        match self {
+            Self::Real => run(pattern),
+            Self::Chroot(root) => Ok(run(&append(root.path(), pattern))?
+                .into_iter()
+                .filter_map(|p| {
+                    let relative = p.strip_prefix(root.path()).ok()?;
+                    Some(match pattern.has_root() {
+                        true => Path::new("/").join(relative),
+                        false => relative.to_path_buf(),
+                    })
+                })
+                .collect()),
+            Self::Fake(_) => panic!("unimplemented"),
+        }
+    }
+
     /// Returns a stream over the entries within a directory.
     ///
     /// This is a proxy to [`tokio::fs::read_dir`].
@@ -505,6 +590,27 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_glob() {
+        let fs = Fs::new();
+        fs.create_dir_all("/glob/sub").await.unwrap();
+        for f in [
+            "/glob/b.kdl",
+            "/glob/a.kdl",
+            "/glob/c.txt",
+            "/glob/sub/d.kdl",
+        ] {
+            fs.write(f, "").await.unwrap();
+        }
+        assert_eq!(fs.glob("/glob/*.kdl").unwrap(), vec![
+            PathBuf::from("/glob/a.kdl"),
+            PathBuf::from("/glob/b.kdl")
+        ]);
+        assert_eq!(fs.glob("/glob/**/*.kdl").unwrap().len(), 3);
+        assert!(fs.glob("/glob/*.json").unwrap().is_empty());
+        assert!(fs.glob("/glob/[").is_err());
+    }
+
     macro_rules! test_append_cases {
     ($(
         $name:ident: ($a:expr, $b:expr) => $expected:expr
@@ -1,17 +1,22 @@
 use {
     crate::{
         Result,
-        agent::{Agent, ToolTarget},
-        generator::AgentResult,
+        agent::{Agent, ToolTarget, hook::HookTrigger},
+        check::{CheckReport, CheckStatus, Finding},
+        generator::{AgentResult, CommandCheck, HookRun},
         source::KdlSources,
     },
     colored::Colorize,
     miette::{Context, GraphicalReportHandler, GraphicalTheme, IntoDiagnostic},
-    std::fmt::Display,
+    serde::Serialize,
+    std::{collections::BTreeMap, fmt::Display},
     super_table::{modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, *},
     tracing::enabled,
 };
 
+mod junit;
+mod sarif;
+
 pub fn print_error(e: &crate::Error) {
     match e {
         crate::Error::DeserializeError(file, kdl_err) => {
@@ -20,6 +25,21 @@ pub fn print_error(e: &crate::Error) {
             handler.render_report(&mut output, kdl_err).unwrap();
             eprintln!("{}\nFile location: '{}'", output, file);
         }
+        crate::Error::UnknownFields(unknown) => {
+            let mut output = String::new();
+            let handler = GraphicalReportHandler::new_themed(GraphicalTheme::unicode());
+            handler.render_report(&mut output, unknown).unwrap();
+            eprintln!("{}\nFile location: '{}'", output, unknown.file);
+        }
+        crate::Error::InvalidAgent {
+            file: Some(file),
+            line,
+            ..
+        } => {
+            let line = line.map(|l| format!(":{l}")).unwrap_or_default();
+            eprintln!("File location: '{}{line}'", file.display());
+        }
+        crate::Error::InvalidAgent { .. } => {}
     };
 }
 
@@ -51,12 +71,18 @@ pub enum OutputFormatArg {
     #[default]
     Table,
     Json,
+    /// SARIF 2.1.0, for code scanning annotations
+    Sarif,
+    /// JUnit XML, for test report annotations
+    Junit,
 }
 
 #[derive(Copy, Clone, Debug)]
 pub enum OutputFormat {
     Table(bool),
     Json,
+    Sarif,
+    Junit,
 }
 
 impl Default for OutputFormat {
@@ -70,14 +96,29 @@ impl Display for OutputFormatArg {
         match self {
             Self::Table => write!(f, "table"),
             Self::Json => write!(f, "json"),
+            Self::Sarif => write!(f, "sarif"),
+            Self::Junit => write!(f, "junit"),
         }
     }
 }
 
+/// `--format json` with `--check-commands`
+#[derive(Serialize)]
+struct JsonChecked<'a> {
+    agents: Vec<&'a Agent>,
+    commands: BTreeMap<&'a str, &'a Vec<CommandCheck>>,
+}
+
 pub(crate) fn agent_header() -> Cell {
     Cell::new(format!("Agent {}", emojis_rs::EMOJI_ROBOT))
 }
 
+fn profile_label(profile: Option<&str>) -> String {
+    profile
+        .map(|p| format!(" [profile {p}]"))
+        .unwrap_or_default()
+}
+
 fn serialize_yaml(label: &str, values: &[String]) -> Option<Cell> {
     if values.is_empty() {
         return None;
@@ -114,7 +155,7 @@ impl OutputFormat {
                 eprintln!("{table}");
                 Ok(())
             }
-            Self::Json => Ok(()),
+            Self::Json | Self::Sarif | Self::Junit => Ok(()),
         }
     }
 
@@ -132,10 +173,10 @@ impl OutputFormat {
         // Location: 🏠 for global, 📁 for local
         let location = if result.is_template() {
             Cell::new("")
-        } else if result.destination.is_absolute() {
-            Cell::new("🏠")
-        } else {
+        } else if result.local {
             Cell::new("📁")
+        } else {
+            Cell::new("🏠")
         };
         row.add_cell(location);
 
@@ -149,6 +190,9 @@ impl OutputFormat {
         servers.sort();
         row.add_cell(Cell::new(servers.join(", ")));
 
+        // Tools, `*` expanded with the built-in catalog
+        row.add_cell(Cell::new(result.tools.join(", ")));
+
         // Allowed tools
         let mut allowed_tools: Vec<String> = result
             .agent
@@ -191,8 +235,16 @@ impl OutputFormat {
         }
 
         // resources
-        if let Some(resources) = serialize_yaml("", &Vec::from_iter(result.resources())) {
-            row.add_cell(resources);
+        match &result.resource_reports {
+            Some(reports) if !reports.is_empty() => {
+                let lines: Vec<String> = reports.iter().map(|r| format!("- {r}")).collect();
+                row.add_cell(Cell::new(lines.join("\n")));
+            }
+            _ => {
+                if let Some(resources) = serialize_yaml("", &Vec::from_iter(result.resources())) {
+                    row.add_cell(resources);
+                }
+            }
         }
 
         match forced.len() {
@@ -226,11 +278,201 @@ impl OutputFormat {
         cell
     }
 
+    /// `--check-commands` results, one row per command
+    fn command_checks(&self, show_templates: bool, results: &[AgentResult]) {
+        let checked: Vec<&AgentResult> = results
+            .iter()
+            .filter(|r| show_templates || !r.is_template())
+            .filter(|r| r.command_checks.as_ref().is_some_and(|c| !c.is_empty()))
+            .collect();
+        if checked.is_empty() {
+            return;
+        }
+        let mut table = Table::new();
+        table
+            .load_preset(UTF8_FULL)
+            .apply_modifier(UTF8_ROUND_CORNERS)
+            .set_content_arrangement(ContentArrangement::Dynamic)
+            .set_header(vec![
+                agent_header(),
+                Cell::new("Source"),
+                Cell::new("Command"),
+                Cell::new("Result"),
+            ]);
+        for result in checked {
+            for check in result.command_checks.iter().flatten() {
+                let color = if check.is_ok() {
+                    Color::Green
+                } else {
+                    Color::Red
+                };
+                table.add_row(vec![
+                    Cell::new(&result.agent.name),
+                    Cell::new(&check.source),
+                    Cell::new(&check.command),
+                    self.maybe_color(Cell::new(check.to_string()), color),
+                ]);
+            }
+        }
+        println!("\n{table}");
+    }
+
+    /// `kg check` summary, plain lines for CI logs
+    pub fn check(&self, report: &CheckReport) -> Result<()> {
+        match self {
+            Self::Table(color) => {
+                for finding in &report.findings {
+                    println!("{}: {finding}", finding.severity);
+                }
+                for drift in &report.drift {
+                    println!("drift: {drift}");
+                }
+                let count = |n: usize, what: &str| match n {
+                    1 => format!("1 {what}"),
+                    n => format!("{n} {what}s"),
+                };
+                let summary = format!(
+                    "{} (exit {}): {}, {}, {}",
+                    report.status,
+                    report.exit_code(),
+                    count(report.agents, "agent"),
+                    count(report.findings.len(), "warning"),
+                    count(report.drift.len(), "drifted file")
+                );
+                // --color always also colors a pipe, e.g. a CI log
+                colored::control::set_override(*color);
+                match report.status {
+                    CheckStatus::Ok => println!("{}", summary.green()),
+                    _ => println!("{}", summary.red()),
+                }
+                Ok(())
+            }
+            Self::Json => {
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(report)
+                        .into_diagnostic()
+                        .wrap_err("unable to serialize check report")?
+                );
+                Ok(())
+            }
+            Self::Sarif => {
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&sarif::sarif(
+                        report,
+                        &std::env::current_dir().into_diagnostic()?
+                    ))
+                    .into_diagnostic()
+                    .wrap_err("unable to serialize SARIF log")?
+                );
+                Ok(())
+            }
+            Self::Junit => {
+                print!("{}", junit::junit(report).into_diagnostic()?);
+                Ok(())
+            }
+        }
+    }
+
+    /// `kg hook run` results, one row per hook
+    pub fn hook_runs(&self, runs: &[HookRun]) -> Result<()> {
+        match self {
+            Self::Table(_) => {
+                let mut table = Table::new();
+                table
+                    .load_preset(UTF8_FULL)
+                    .apply_modifier(UTF8_ROUND_CORNERS)
+                    .set_content_arrangement(ContentArrangement::Dynamic)
+                    .set_header(vec![
+                        Cell::new("Hook"),
+                        Cell::new("Command"),
+                        Cell::new("Result"),
+                        Cell::new("Stdout"),
+                        Cell::new("Stderr"),
+                    ]);
+                for run in runs {
+                    let color = match (run.skipped.is_some(), run.is_ok()) {
+                        (true, _) => Color::Yellow,
+                        (false, true) => Color::Green,
+                        (false, false) => Color::Red,
+                    };
+                    let stdout = match run.truncated {
+                        true => format!("{}\n… truncated", run.stdout),
+                        false => run.stdout.clone(),
+                    };
+                    table.add_row(vec![
+                        Cell::new(format!("{} {}", run.trigger, run.name)),
+                        Cell::new(&run.command),
+                        self.maybe_color(Cell::new(run.to_string()), color),
+                        Cell::new(stdout.trim_end()),
+                        Cell::new(run.stderr.trim_end()),
+                    ]);
+                }
+                println!("{table}");
+                Ok(())
+            }
+            // SARIF and JUnit report config checks, hook runs print as JSON
+            Self::Json | Self::Sarif | Self::Junit => {
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(runs)
+                        .into_diagnostic()
+                        .wrap_err("unable to serialize hook results")?
+                );
+                Ok(())
+            }
+        }
+    }
+
+    /// Hooks of every agent in the order Kiro sees them, disabled ones
+    /// marked
+    fn hooks(&self, show_templates: bool, results: &[AgentResult]) {
+        let mut table = Table::new();
+        table
+            .load_preset(UTF8_FULL)
+            .apply_modifier(UTF8_ROUND_CORNERS)
+            .set_content_arrangement(ContentArrangement::Dynamic)
+            .set_header(vec![
+                agent_header(),
+                Cell::new(format!("Hook {}", emojis_rs::EMOJI_LINK)),
+                Cell::new("Command"),
+            ]);
+        for result in results
+            .iter()
+            .filter(|r| show_templates || !r.is_template())
+        {
+            for trigger in enum_iterator::all::<HookTrigger>() {
+                for (name, hook) in result.agent.hook.named(&trigger) {
+                    let hook_cell = match hook.disabled {
+                        true => self.maybe_color(
+                            Cell::new(format!("{trigger} {name} (disabled)")),
+                            Color::DarkGrey,
+                        ),
+                        false => Cell::new(format!("{trigger} {name}")),
+                    };
+                    table.add_row(vec![
+                        Cell::new(&result.agent.name),
+                        hook_cell,
+                        Cell::new(&hook.command),
+                    ]);
+                }
+            }
+        }
+        if !table.is_empty() {
+            println!("\n{table}");
+        }
+    }
+
+    /// `findings` are only reported by SARIF and JUnit, the table and JSON
+    /// leave them to the log
     pub fn result(
         &self,
         dry_run: bool,
         show_templates: bool,
+        profile: Option<&str>,
         results: Vec<AgentResult>,
+        findings: Vec<Finding>,
     ) -> Result<()> {
         match self {
             Self::Table(_) => {
@@ -244,7 +486,11 @@ impl OutputFormat {
                 if dry_run {
                     table.set_header(vec![
                         self.maybe_color(
-                            Cell::new(format!("Agent {} (PREVIEW)", emojis_rs::EMOJI_ROBOT)),
+                            Cell::new(format!(
+                                "{} (PREVIEW){}",
+                                agent_header().content(),
+                                profile_label(profile)
+                            )),
                             Color::Yellow,
                         ),
                         self.maybe_color(Cell::new("Loc"), Color::Yellow),
@@ -252,6 +498,10 @@ impl OutputFormat {
                             Cell::new(format!("MCP {}", emojis_rs::EMOJI_COMPUTER)),
                             Color::Yellow,
                         ),
+                        self.maybe_color(
+                            Cell::new(format!("Tools {}", emojis_rs::EMOJI_WRENCH)),
+                            Color::Yellow,
+                        ),
                         self.maybe_color(
                             Cell::new(format!("Allowed Tools {}", emojis_rs::EMOJI_GEAR)),
                             Color::Yellow,
@@ -269,9 +519,14 @@ impl OutputFormat {
                     ]);
                 } else {
                     table.set_header(vec![
-                        agent_header(),
+                        Cell::new(format!(
+                            "{}{}",
+                            agent_header().content(),
+                            profile_label(profile)
+                        )),
                         Cell::new("Loc"),
                         Cell::new(format!("MCP {}", emojis_rs::EMOJI_COMPUTER)),
+                        Cell::new(format!("Tools {}", emojis_rs::EMOJI_WRENCH)),
                         Cell::new(format!("Allowed Tools {}", emojis_rs::EMOJI_GEAR)),
                         Cell::new(format!("Resources {}", emojis_rs::EMOJI_DOCUMENT)),
                         Cell::new("Override (Allowed) Permissions")
@@ -287,6 +542,11 @@ impl OutputFormat {
                 }
 
                 println!("{table}");
+                self.hooks(show_templates, &results);
+                self.command_checks(show_templates, &results);
+                if let Some(profile) = profile {
+                    println!("\nProfile {}", profile.bold());
+                }
                 if dry_run {
                     println!("\n{} Config is valid", emojis_rs::EMOJI_SUCCESS);
                     println!(
@@ -300,15 +560,24 @@ impl OutputFormat {
                 Ok(())
             }
             Self::Json => {
-                let kiro_agents: Vec<Agent> = results.into_iter().map(|a| a.kiro_agent).collect();
+                let agents: Vec<&Agent> = results.iter().map(|a| &a.kiro_agent).collect();
+                // only --check-commands sets command_checks
+                let commands: BTreeMap<&str, &Vec<CommandCheck>> = results
+                    .iter()
+                    .filter_map(|r| Some((r.agent.name.as_str(), r.command_checks.as_ref()?)))
+                    .collect();
+                let json = match results.iter().any(|r| r.command_checks.is_some()) {
+                    true => serde_json::to_string_pretty(&JsonChecked { agents, commands }),
+                    false => serde_json::to_string_pretty(&agents),
+                };
                 println!(
                     "{}",
-                    serde_json::to_string_pretty(&kiro_agents)
-                        .into_diagnostic()
-                        .wrap_err("todo")?
+                    json.into_diagnostic()
+                        .wrap_err("unable to serialize generated agents")?
                 );
                 Ok(())
             }
+            Self::Sarif | Self::Junit => self.check(&CheckReport::new(&results, findings, vec![])),
         }
     }
 }
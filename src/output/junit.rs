@@ -0,0 +1,162 @@
+use {
+    crate::check::{CheckReport, Severity},
+    std::fmt::Write,
+};
+
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// A failed test case: an error, a warning or a drifted file
+struct Case {
+    class: String,
+    name: String,
+    file: Option<String>,
+    line: Option<usize>,
+    severity: Severity,
+    kind: &'static str,
+    text: String,
+}
+
+impl Case {
+    fn write(&self, xml: &mut String) -> std::fmt::Result {
+        write!(
+            xml,
+            r#"    <testcase classname="{}" name="{}""#,
+            escape(&self.class),
+            escape(&self.name)
+        )?;
+        if let Some(file) = &self.file {
+            write!(xml, r#" file="{}""#, escape(file))?;
+        }
+        if let Some(line) = self.line {
+            write!(xml, r#" line="{line}""#)?;
+        }
+        let element = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "failure",
+        };
+        writeln!(xml, ">")?;
+        writeln!(
+            xml,
+            r#"      <{element} type="{}" message="{}">{}</{element}>"#,
+            self.kind,
+            escape(&self.name),
+            escape(&self.text)
+        )?;
+        writeln!(xml, "    </testcase>")
+    }
+}
+
+/// `report` as a JUnit XML test suite, one failed test case per finding
+/// and drifted file, a single passing one when there are none
+pub(super) fn junit(report: &CheckReport) -> Result<String, std::fmt::Error> {
+    let mut cases: Vec<Case> = report
+        .findings
+        .iter()
+        .map(|finding| Case {
+            class: format!("kg.{}", finding.agent.as_deref().unwrap_or("config")),
+            name: finding.message.clone(),
+            file: finding.file.as_ref().map(|f| f.display().to_string()),
+            line: finding.line,
+            severity: finding.severity,
+            kind: match finding.severity {
+                Severity::Error => "config-error",
+                Severity::Warning => "policy-violation",
+            },
+            text: finding.to_string(),
+        })
+        .collect();
+    cases.extend(report.drift.iter().map(|drift| Case {
+        class: "kg.drift".to_string(),
+        name: drift.to_string(),
+        file: Some(drift.path.display().to_string()),
+        line: None,
+        severity: Severity::Warning,
+        kind: "output-drift",
+        text: format!("{drift}, run kg generate"),
+    }));
+    let errors = cases
+        .iter()
+        .filter(|c| c.severity == Severity::Error)
+        .count();
+    let failures = cases.len() - errors;
+    let tests = cases.len().max(1);
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let counts = format!(r#"tests="{tests}" failures="{failures}" errors="{errors}""#);
+    writeln!(xml, r#"<testsuites name="kg" {counts}>"#)?;
+    writeln!(xml, r#"  <testsuite name="kg config" {counts}>"#)?;
+    if cases.is_empty() {
+        writeln!(
+            xml,
+            r#"    <testcase classname="kg" name="{} agents checked"/>"#,
+            report.agents
+        )?;
+    }
+    for case in &cases {
+        case.write(&mut xml)?;
+    }
+    xml.push_str("  </testsuite>\n</testsuites>\n");
+    Ok(xml)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{check::Finding, generator::Drift},
+    };
+
+    #[test]
+    fn test_junit() -> std::fmt::Result {
+        let xml = junit(&CheckReport::new(&[], vec![], vec![]))?;
+        assert!(
+            xml.contains(r#"tests="1" failures="0" errors="0""#),
+            "{xml}"
+        );
+        assert!(xml.contains(r#"name="0 agents checked"/>"#), "{xml}");
+
+        let mut warning = Finding::warning("agent 'dev': matcher '<x>' matches no tool")
+            .at(".kiro/generators/kg.kdl", Some(7));
+        warning.agent = Some("dev".into());
+        let report = CheckReport::new(
+            &[],
+            vec![
+                warning,
+                Finding::error("unknown node `tols` in agent").at("dev.kdl", Some(2)),
+            ],
+            vec![Drift::changed(".kiro/agents/dev.json".into())],
+        );
+        let xml = junit(&report)?;
+        assert!(
+            xml.contains(r#"tests="3" failures="2" errors="1""#),
+            "{xml}"
+        );
+        assert!(
+            xml.contains(
+                r#"<testcase classname="kg.dev" name="agent &apos;dev&apos;: matcher &apos;&lt;x&gt;&apos; matches no tool" file=".kiro/generators/kg.kdl" line="7">"#
+            ),
+            "{xml}"
+        );
+        assert!(xml.contains(r#"<error type="config-error""#), "{xml}");
+        assert!(
+            xml.contains(
+                r#"<failure type="output-drift" message=".kiro/agents/dev.json changed">"#
+            ),
+            "{xml}"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,182 @@
+use {
+    crate::check::{CheckReport, Severity},
+    serde_json::{Value, json},
+    std::path::Path,
+};
+
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+const CONFIG_ERROR: &str = "config-error";
+const POLICY_VIOLATION: &str = "policy-violation";
+const OUTPUT_DRIFT: &str = "output-drift";
+
+/// Rule ids and descriptions, one per kind of result
+const RULES: &[(&str, &str)] = &[
+    (CONFIG_ERROR, "The config could not be loaded or is invalid"),
+    (POLICY_VIOLATION, "kg warned about the configuration"),
+    (
+        OUTPUT_DRIFT,
+        "A generated file is missing or differs from what kg generate writes",
+    ),
+];
+
+/// Base of relative artifact uris, the directory kg ran in
+const PROJECT_ROOT: &str = "PROJECTROOT";
+
+fn uri(path: &Path) -> String {
+    path.display().to_string().replace('\\', "/")
+}
+
+/// `file` relative to `root`, or an absolute `file://` uri for files outside
+/// the project, e.g. the global config
+fn artifact_location(file: &Path, root: &Path) -> Value {
+    match file.strip_prefix(root) {
+        _ if file.is_relative() => json!({ "uri": uri(file), "uriBaseId": PROJECT_ROOT }),
+        Ok(relative) => json!({ "uri": uri(relative), "uriBaseId": PROJECT_ROOT }),
+        Err(_) => json!({ "uri": file_uri(file) }),
+    }
+}
+
+/// `file://` uri of an absolute path
+fn file_uri(path: &Path) -> String {
+    let path = uri(path);
+    let slash = if path.starts_with('/') { "" } else { "/" };
+    format!("file://{slash}{path}")
+}
+
+fn result(
+    root: &Path,
+    rule: &str,
+    level: Severity,
+    message: &str,
+    file: Option<&Path>,
+    line: Option<usize>,
+) -> Value {
+    let mut result = json!({
+        "ruleId": rule,
+        "level": level.to_string(),
+        "message": { "text": message },
+    });
+    if let Some(file) = file {
+        // code scanning needs a line to annotate, the top of the file will do
+        result["locations"] = json!([{
+            "physicalLocation": {
+                "artifactLocation": artifact_location(file, root),
+                "region": { "startLine": line.unwrap_or(1) },
+            }
+        }]);
+    }
+    result
+}
+
+/// `report` as a SARIF 2.1.0 log with one run of kg, files are relative to
+/// `root`, the project root
+pub(super) fn sarif(report: &CheckReport, root: &Path) -> Value {
+    let mut results: Vec<Value> = report
+        .findings
+        .iter()
+        .map(|finding| {
+            let rule = match finding.severity {
+                Severity::Error => CONFIG_ERROR,
+                Severity::Warning => POLICY_VIOLATION,
+            };
+            result(
+                root,
+                rule,
+                finding.severity,
+                &finding.message,
+                finding.file.as_deref(),
+                finding.line,
+            )
+        })
+        .collect();
+    results.extend(report.drift.iter().map(|drift| {
+        let message = format!("{drift}, run kg generate");
+        result(
+            root,
+            OUTPUT_DRIFT,
+            Severity::Warning,
+            &message,
+            Some(&drift.path),
+            None,
+        )
+    }));
+    let rules: Vec<Value> = RULES
+        .iter()
+        .map(|(id, text)| json!({ "id": id, "shortDescription": { "text": text } }))
+        .collect();
+    json!({
+        "$schema": SCHEMA,
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "kg",
+                    "version": clap::crate_version!(),
+                    "informationUri": crate::DOCS_URL,
+                    "rules": rules,
+                }
+            },
+            "originalUriBaseIds": {
+                PROJECT_ROOT: { "uri": format!("{}/", file_uri(root).trim_end_matches('/')) },
+            },
+            "results": results,
+        }]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{check::Finding, generator::Drift},
+    };
+
+    #[test]
+    fn test_sarif() {
+        let report = CheckReport::new(
+            &[],
+            vec![
+                Finding::warning("agent 'dev': tools 'fs_raed' is not a built-in tool")
+                    .at(".kiro/generators/kg.kdl", Some(3)),
+                Finding::warning("--match 'x' does not match any agent"),
+                Finding::warning("`allow` in `shell` is deprecated")
+                    .at("/project/.kiro/generators/rust.kdl", Some(2)),
+                Finding::warning("`allow` in `shell` is deprecated")
+                    .at("/home/me/.kiro/generators/kg.kdl", Some(4)),
+            ],
+            vec![Drift::missing(".kiro/agents/dev.json".into())],
+        );
+        let sarif = sarif(&report, Path::new("/project"));
+        assert_eq!(sarif["version"], "2.1.0");
+        assert_eq!(
+            sarif["runs"][0]["originalUriBaseIds"][PROJECT_ROOT]["uri"],
+            "file:///project/"
+        );
+        let results = sarif["runs"][0]["results"].as_array().unwrap();
+        assert_eq!(results.len(), 5);
+        assert_eq!(results[0]["ruleId"], POLICY_VIOLATION);
+        assert_eq!(results[0]["level"], "warning");
+        let location = &results[0]["locations"][0]["physicalLocation"];
+        assert_eq!(
+            location["artifactLocation"]["uri"],
+            ".kiro/generators/kg.kdl"
+        );
+        assert_eq!(location["artifactLocation"]["uriBaseId"], PROJECT_ROOT);
+        assert_eq!(location["region"]["startLine"], 3);
+        assert!(results[1].get("locations").is_none());
+        let artifact =
+            |i: usize| &results[i]["locations"][0]["physicalLocation"]["artifactLocation"];
+        assert_eq!(artifact(2)["uri"], ".kiro/generators/rust.kdl");
+        assert_eq!(
+            artifact(3)["uri"],
+            "file:///home/me/.kiro/generators/kg.kdl"
+        );
+        assert!(artifact(3).get("uriBaseId").is_none());
+        assert_eq!(results[4]["ruleId"], OUTPUT_DRIFT);
+        assert_eq!(
+            results[4]["message"]["text"],
+            ".kiro/agents/dev.json missing, run kg generate"
+        );
+    }
+}
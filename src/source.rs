@@ -1,4 +1,5 @@
 use {
+    crate::{check::Finding, config::MatrixOrigin, generator::ConfigLayer, os::Fs},
     serde::Serialize,
     std::{
         collections::{HashMap, HashSet},
@@ -9,27 +10,91 @@ use {
     super_table::Cell,
 };
 
+/// Where a part of an agent definition was read from
 #[derive(Clone, Serialize)]
-pub enum KdlAgentSource {
-    LocalFile(PathBuf),
-    LocalInline,
-    GlobalFile(PathBuf),
-    GlobalInline,
+pub enum SourceKind {
+    /// `<agent-name>.kdl`
+    File(PathBuf),
+    /// `agent` node in a kg.kdl
+    Inline(PathBuf),
+    /// Expanded from an `agent-matrix` node in a kg.kdl
+    Matrix(PathBuf, MatrixOrigin),
+}
+
+/// One part of an agent definition and the config layer it came from
+#[derive(Clone, Serialize)]
+pub struct KdlAgentSource {
+    pub layer: String,
+    pub local: bool,
+    pub kind: SourceKind,
 }
 
 impl KdlAgentSource {
+    pub fn file(layer: &ConfigLayer, path: PathBuf) -> Self {
+        Self {
+            layer: layer.name.clone(),
+            local: layer.local,
+            kind: SourceKind::File(path),
+        }
+    }
+
+    pub fn inline(layer: &ConfigLayer, path: PathBuf) -> Self {
+        Self {
+            layer: layer.name.clone(),
+            local: layer.local,
+            kind: SourceKind::Inline(path),
+        }
+    }
+
+    pub fn matrix(layer: &ConfigLayer, path: PathBuf, origin: MatrixOrigin) -> Self {
+        Self {
+            layer: layer.name.clone(),
+            local: layer.local,
+            kind: SourceKind::Matrix(path, origin),
+        }
+    }
+
     fn is_local(&self) -> bool {
-        matches!(self, Self::LocalFile(_) | Self::LocalInline)
+        self.local
+    }
+
+    /// File this part was read from and the line of its `agent` or
+    /// `agent-matrix` node, line 1 of an `<agent-name>.kdl`
+    fn locate(&self, fs: &Fs, name: &str) -> (PathBuf, Option<usize>) {
+        let (path, node, arg) = match &self.kind {
+            SourceKind::File(p) => return (p.clone(), Some(1)),
+            SourceKind::Inline(p) => (p, "agent", name),
+            SourceKind::Matrix(p, origin) => (p, "agent-matrix", origin.pattern.as_str()),
+        };
+        let line = fs.read_to_string_sync(path).ok().and_then(|content| {
+            let doc: kdl::KdlDocument = content.parse().ok()?;
+            let node = doc.nodes().iter().find(|n| {
+                n.name().value() == node
+                    && n.entries()
+                        .iter()
+                        .find(|e| e.name().is_none())
+                        .and_then(|e| e.value().as_string())
+                        == Some(arg)
+            })?;
+            let offset = node.span().offset();
+            Some(content.get(..offset)?.matches('\n').count() + 1)
+        });
+        (path.clone(), line)
     }
 }
 
 impl From<&KdlAgentSource> for Cell {
     fn from(value: &KdlAgentSource) -> Self {
-        match value {
-            KdlAgentSource::GlobalInline => Cell::new("global-inline"),
-            KdlAgentSource::GlobalFile(p) => Cell::new(format!("{}", p.display())),
-            KdlAgentSource::LocalInline => Cell::new("local-inline"),
-            KdlAgentSource::LocalFile(p) => Cell::new(format!("{}", p.display())),
+        match &value.kind {
+            SourceKind::File(p) => Cell::new(format!("{}\n[{}]", p.display(), value.layer)),
+            SourceKind::Inline(p) => {
+                Cell::new(format!("{} (inline)\n[{}]", p.display(), value.layer))
+            }
+            SourceKind::Matrix(p, origin) => Cell::new(format!(
+                "{} (matrix {origin})\n[{}]",
+                p.display(),
+                value.layer
+            )),
         }
     }
 }
@@ -56,6 +121,23 @@ impl KdlSources {
         false
     }
 
+    /// Point findings about an agent without a place of their own at the
+    /// agent definition with the highest precedence, local before global
+    pub fn locate(&self, fs: &Fs, findings: &mut [Finding]) {
+        for finding in findings.iter_mut().filter(|f| f.file.is_none()) {
+            let Some(sources) = finding.agent.as_ref().and_then(|a| self.get(a)) else {
+                continue;
+            };
+            let source = sources.iter().rev().find(|s| s.is_local());
+            if let Some(source) = source.or_else(|| sources.last()) {
+                let agent = finding.agent.clone().unwrap_or_default();
+                let (file, line) = source.locate(fs, &agent);
+                finding.file = Some(file);
+                finding.line = line;
+            }
+        }
+    }
+
     fn add(&mut self, name: &str) {
         self.0.insert(name.to_string(), Vec::with_capacity(4));
     }